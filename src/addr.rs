@@ -0,0 +1,61 @@
+use core::fmt;
+
+/// an address in the 16-bit `avnera` address space.
+///
+/// all arithmetic on `Addr16` wraps at the ends of the address space, so branch resolution,
+/// distances, and range checks agree on what happens around `0xffff`.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Addr16(pub u16);
+
+impl Addr16 {
+    /// `self + n`, wrapping around the end of the address space.
+    pub fn wrapping_add(self, n: u16) -> Addr16 {
+        Addr16(self.0.wrapping_add(n))
+    }
+
+    /// `self - n`, wrapping around the start of the address space.
+    pub fn wrapping_sub(self, n: u16) -> Addr16 {
+        Addr16(self.0.wrapping_sub(n))
+    }
+
+    /// `self` displaced by the signed offset `rel`, wrapping around either end of the address
+    /// space.
+    pub fn offset(self, rel: i16) -> Addr16 {
+        Addr16(self.0.wrapping_add(rel as u16))
+    }
+
+    /// how many bytes forward from `self` `to` is, wrapping around the end of the address space.
+    pub fn distance_to(self, to: Addr16) -> u16 {
+        to.0.wrapping_sub(self.0)
+    }
+
+    /// the signed displacement from `self` to `to`. this is the nearest way to get from `self` to
+    /// `to`, going either forward or backward.
+    pub fn displacement_to(self, to: Addr16) -> i16 {
+        self.distance_to(to) as i16
+    }
+
+    /// is `self` in the `len` bytes starting at `start`? ranges may wrap around the end of the
+    /// address space, and `len` may be up to `0x10000` to describe the entire space.
+    pub fn is_within(self, start: Addr16, len: u32) -> bool {
+        (start.distance_to(self) as u32) < len
+    }
+}
+
+impl From<u16> for Addr16 {
+    fn from(addr: u16) -> Addr16 {
+        Addr16(addr)
+    }
+}
+
+impl From<Addr16> for u16 {
+    fn from(addr: Addr16) -> u16 {
+        addr.0
+    }
+}
+
+impl fmt::Display for Addr16 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:04x}", self.0)
+    }
+}
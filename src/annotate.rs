@@ -0,0 +1,122 @@
+use core::fmt;
+
+use yaxpeax_arch::annotation::{AnnotatingDecoder, DescriptionSink};
+use yaxpeax_arch::{Arch, Decoder, Reader};
+
+use crate::{Avnera, InstDecoder, Instruction, Opcode, Operand, OperandSpec};
+
+/// a field of an `avnera` instruction's encoding, as reported by [`InstDecoder`]'s
+/// [`AnnotatingDecoder`] impl.
+///
+/// fields are reported as inclusive ranges of bits, counted from the least significant bit of the
+/// opcode byte; the first byte after the opcode is bits `8..15`, and so on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FieldDescription {
+    /// bits selecting the opcode. this is the high five bits of the opcode byte if the low three
+    /// select an operand, and the whole byte otherwise.
+    Opcode { opcode: Opcode },
+    /// the low three bits of the opcode byte, selecting the register or register pair of operand
+    /// `operand`.
+    RegisterSelector { operand: u8, value: Operand },
+    /// an immediate for operand `operand`, either in the low three bits of the opcode byte or in
+    /// bytes following it. absolute addresses are immediates here too.
+    Immediate { operand: u8, value: Operand },
+    /// the byte following the opcode holding a branch displacement or an offset from a register
+    /// pair, for operand `operand`.
+    Displacement { operand: u8, value: Operand },
+}
+
+impl yaxpeax_arch::annotation::FieldDescription for FieldDescription {
+    fn id(&self) -> u32 {
+        match self {
+            FieldDescription::Opcode { .. } => 0,
+            FieldDescription::RegisterSelector { operand, .. } => 1 + *operand as u32 * 3,
+            FieldDescription::Immediate { operand, .. } => 2 + *operand as u32 * 3,
+            FieldDescription::Displacement { operand, .. } => 3 + *operand as u32 * 3,
+        }
+    }
+
+    fn is_separator(&self) -> bool {
+        false
+    }
+}
+
+impl fmt::Display for FieldDescription {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldDescription::Opcode { opcode } => {
+                write!(f, "opcode ({})", opcode)
+            }
+            FieldDescription::RegisterSelector { value, .. } => {
+                write!(f, "register selector ({})", value)
+            }
+            FieldDescription::Immediate { value, .. } => {
+                write!(f, "immediate ({})", value)
+            }
+            FieldDescription::Displacement { value, .. } => {
+                write!(f, "displacement ({})", value)
+            }
+        }
+    }
+}
+
+impl AnnotatingDecoder<Avnera> for InstDecoder {
+    type FieldDescription = FieldDescription;
+
+    fn decode_with_annotation<
+        T: Reader<<Avnera as Arch>::Address, <Avnera as Arch>::Word>,
+        S: DescriptionSink<Self::FieldDescription>
+    >(&self, inst: &mut Instruction, words: &mut T, sink: &mut S) -> Result<(), <Avnera as Arch>::DecodeError> {
+        self.decode_into(inst, words)?;
+
+        // overrides are required to decode with their opcode's own operands, so the opcode alone
+        // says where each operand came from.
+        let specs = inst.opcode.operand_specs();
+
+        let low3 = specs.iter().position(|spec| matches!(spec,
+            OperandSpec::RegisterLow3 |
+            OperandSpec::RegisterPairLow3 |
+            OperandSpec::MemRegIndirectLow3 |
+            OperandSpec::MemRegIndirectOffsetLow3 |
+            OperandSpec::ImmLow3 |
+            OperandSpec::BitLow3
+        ));
+        let opcode_start = if low3.is_some() { 3 } else { 0 };
+        sink.record(opcode_start, 7, FieldDescription::Opcode { opcode: inst.opcode });
+
+        let mut bit = 8;
+        for (i, (spec, value)) in specs.iter().zip(inst.operands.iter()).enumerate() {
+            let operand = i as u8;
+            let value = *value;
+            match spec {
+                OperandSpec::Nothing => { break; }
+                OperandSpec::OpcodeByte => {}
+                OperandSpec::RegisterLow3 |
+                OperandSpec::RegisterPairLow3 |
+                OperandSpec::MemRegIndirectLow3 => {
+                    sink.record(0, 2, FieldDescription::RegisterSelector { operand, value });
+                }
+                OperandSpec::MemRegIndirectOffsetLow3 => {
+                    sink.record(0, 2, FieldDescription::RegisterSelector { operand, value });
+                    sink.record(bit, bit + 7, FieldDescription::Displacement { operand, value });
+                }
+                OperandSpec::ImmLow3 |
+                OperandSpec::BitLow3 => {
+                    sink.record(0, 2, FieldDescription::Immediate { operand, value });
+                }
+                OperandSpec::BranchRelI8 => {
+                    sink.record(bit, bit + 7, FieldDescription::Displacement { operand, value });
+                }
+                OperandSpec::ImmU8 |
+                OperandSpec::ImmU16 |
+                OperandSpec::MemAbs16 => {
+                    let end = bit + spec.trailing_bytes() as u32 * 8 - 1;
+                    sink.record(bit, end, FieldDescription::Immediate { operand, value });
+                }
+            }
+            bit += spec.trailing_bytes() as u32 * 8;
+        }
+
+        Ok(())
+    }
+}
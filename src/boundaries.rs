@@ -0,0 +1,148 @@
+use yaxpeax_arch::{Decoder, U8Reader};
+
+use crate::{InstDecoder, Instruction};
+
+/// the longest encoding of any `avnera` instruction, in bytes.
+pub(crate) const MAX_INSTRUCTION_LEN: u16 = 3;
+
+/// a precomputed set of instruction-start addresses over an image.
+///
+/// the index is built by a linear sweep, and is a bitset over the entire 16-bit address space, so
+/// answering whether an address starts an instruction is a single bit test. instructions are at
+/// most three bytes long, so finding the previous instruction start only ever looks at the three
+/// addresses before the query.
+///
+/// bytes that do not decode are not recorded as instruction starts; the sweep resumes at the next
+/// byte.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct InstructionBoundaries {
+    starts: [u64; 0x10000 / 64],
+}
+
+impl core::fmt::Debug for InstructionBoundaries {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let count: u32 = self.starts.iter().map(|w| w.count_ones()).sum();
+        write!(f, "InstructionBoundaries {{ {} starts }}", count)
+    }
+}
+
+impl InstructionBoundaries {
+    /// linearly sweep `data`, mapped at address `base`, recording the address of each instruction
+    /// `decoder` decodes.
+    ///
+    /// a [permissive](InstDecoder::permissive) decoder records every undecodable byte as a
+    /// one-byte instruction, and an index built with extensions or overrides agrees with a decoder
+    /// using them. addresses past `0xffff` are not representable, so data beyond the end of the address space
+    /// is ignored. an instruction that would run off the end of `data` is not recorded.
+    pub fn from_linear_sweep(decoder: &InstDecoder, data: &[u8], base: u16) -> InstructionBoundaries {
+        let mut index = InstructionBoundaries {
+            starts: [0; 0x10000 / 64],
+        };
+
+        let limit = core::cmp::min(data.len(), 0x10000 - base as usize);
+        let data = &data[..limit];
+
+        let mut offset = 0usize;
+        while offset < data.len() {
+            let mut reader = U8Reader::new(&data[offset..]);
+            match decoder.decode(&mut reader) {
+                Ok(inst) => {
+                    index.insert(base + offset as u16);
+                    offset += inst.len() as usize;
+                }
+                Err(_) => {
+                    offset += 1;
+                }
+            }
+        }
+
+        index
+    }
+
+    fn insert(&mut self, addr: u16) {
+        self.starts[addr as usize / 64] |= 1 << (addr % 64);
+    }
+
+    /// is there an instruction starting at `addr`?
+    pub fn is_instruction_start(&self, addr: u16) -> bool {
+        self.starts[addr as usize / 64] & (1 << (addr % 64)) != 0
+    }
+
+    /// the address of the nearest instruction start in the three bytes before `addr`.
+    ///
+    /// because instructions are no longer than three bytes, an instruction start further back
+    /// than that cannot be the instruction immediately preceding `addr`; in that case `addr` is
+    /// preceded by undecodable bytes and this returns `None`.
+    pub fn prev_instruction(&self, addr: u16) -> Option<u16> {
+        (1..=MAX_INSTRUCTION_LEN)
+            .filter_map(|back| addr.checked_sub(back))
+            .find(|candidate| self.is_instruction_start(*candidate))
+    }
+}
+
+/// how many instructions [`InstDecoder::prev_instruction_at`] will backtrack through when
+/// comparing candidate previous instructions.
+const BACKTRACK_DEPTH: u8 = 6;
+
+impl InstDecoder {
+    /// find the most plausible instruction ending immediately before `addr`, given `data` mapped
+    /// at address `base`.
+    ///
+    /// without an [`InstructionBoundaries`] index there is no single right answer: the byte before
+    /// `addr` may be a one-byte instruction, or the last operand byte of a longer one. each
+    /// candidate is scored by how many instructions can be decoded back-to-back before it (up to
+    /// a small bound), or reaching the start of `data` exactly. the candidate with the longest
+    /// such chain wins, with ties going to the longer instruction, since operand bytes of long
+    /// instructions very often also decode as one-byte instructions.
+    ///
+    /// returns the address and decoded form of the chosen instruction, or `None` if no
+    /// instruction ends at `addr`.
+    pub fn prev_instruction_at(&self, data: &[u8], base: u16, addr: u16) -> Option<(u16, Instruction)> {
+        let end = addr.checked_sub(base)? as usize;
+        if end > data.len() {
+            return None;
+        }
+
+        let mut best: Option<(u8, usize, Instruction)> = None;
+        for back in (1..=MAX_INSTRUCTION_LEN as usize).rev() {
+            let start = match end.checked_sub(back) {
+                Some(start) => start,
+                None => continue,
+            };
+            let inst = match self.decode_ending_at(data, start, end) {
+                Some(inst) => inst,
+                None => continue,
+            };
+            let depth = self.chain_depth(data, start, BACKTRACK_DEPTH);
+            if best.as_ref().map(|(best_depth, _, _)| depth > *best_depth).unwrap_or(true) {
+                best = Some((depth, start, inst));
+            }
+        }
+
+        best.map(|(_, start, inst)| (base + start as u16, inst))
+    }
+
+    fn decode_ending_at(&self, data: &[u8], start: usize, end: usize) -> Option<Instruction> {
+        let inst = self.decode(&mut U8Reader::new(&data[start..end])).ok()?;
+        if start + inst.len() as usize == end {
+            Some(inst)
+        } else {
+            None
+        }
+    }
+
+    /// the length of the longest chain of instructions, up to `limit`, that ends exactly at
+    /// `end`. reaching the start of `data` counts as a chain of `limit`.
+    fn chain_depth(&self, data: &[u8], end: usize, limit: u8) -> u8 {
+        if end == 0 || limit == 0 {
+            return limit;
+        }
+
+        (1..=MAX_INSTRUCTION_LEN as usize)
+            .filter_map(|back| end.checked_sub(back))
+            .filter(|start| self.decode_ending_at(data, *start, end).is_some())
+            .map(|start| 1 + self.chain_depth(data, start, limit - 1))
+            .max()
+            .unwrap_or(0)
+    }
+}
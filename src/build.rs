@@ -0,0 +1,165 @@
+use core::fmt;
+
+use crate::boundaries::MAX_INSTRUCTION_LEN;
+use crate::{DecodeErrorKind, InstDecoder, Instruction, Opcode, Operand, OperandKind, OperandSpec};
+
+/// the reason [`Instruction::build`] rejected an opcode and operands.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum BuildError {
+    /// the opcode takes `expected` operands, but a different number were provided.
+    OperandCount { expected: u8 },
+    /// the operand at `index` is not of the kind this opcode takes there.
+    OperandKind { index: u8, expected: OperandKind },
+    /// the operand at `index` is of the right kind, but its value can not be encoded with this
+    /// opcode. this is the case for immediates taken from the low bits of an opcode byte where
+    /// some values select a different opcode entirely; `op5xhi 0x01` would be `scf`.
+    OperandValue { index: u8 },
+    /// the instruction would be `length` bytes long, longer than any `avnera` instruction. this is
+    /// only possible for an `Opcode::Extension` whose operands take more than two bytes.
+    TooLong { length: u8 },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::OperandCount { expected } => {
+                write!(f, "expected {} operands", expected)
+            }
+            BuildError::OperandKind { index, expected } => {
+                write!(f, "expected operand {} to be {:?}", index, expected)
+            }
+            BuildError::OperandValue { index } => {
+                write!(f, "operand {} is not encodable for this opcode", index)
+            }
+            BuildError::TooLong { length } => {
+                write!(f, "instruction would be {} bytes long, longer than any instruction", length)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BuildError {}
+
+impl Opcode {
+    /// the operands this opcode is decoded with, in order.
+    pub(crate) fn operand_specs(&self) -> &'static [OperandSpec] {
+        use Opcode::*;
+        use OperandSpec::*;
+
+        match self {
+            Inc | Adc | MovRnR0 | Or | And | Xor | Rcl | Rcr | Dec | Sbc | Add |
+            MovR0Rn | Cmp | Push | Pop => &[RegisterLow3],
+            Op5xHi | Op6xHi => &[ImmLow3],
+            Bit => &[BitLow3],
+            Scf | Ccf | Ret | Iret => &[],
+            Jnz | Jnc | Jz | Jc => &[BranchRelI8],
+            JccLo | JccHi => &[ImmLow3, BranchRelI8],
+            Jmp | Call => &[ImmU16],
+            IncW => &[RegisterPairLow3],
+            StoreAbs16 | LoadAbs16 => &[RegisterLow3, MemAbs16],
+            LoadImm8 => &[RegisterLow3, ImmU8],
+            StoreRegPair | LoadRegPair => &[MemRegIndirectLow3],
+            StoreRegPairC | LoadRegPairC => &[MemRegIndirectOffsetLow3],
+            Undefined => &[OpcodeByte],
+            Extension(ext) => {
+                let count = ext.operands.iter()
+                    .position(|spec| *spec == Nothing)
+                    .unwrap_or(ext.operands.len());
+                &ext.operands[..count]
+            }
+        }
+    }
+
+    /// can the low-three-bit immediate `imm` be encoded for this opcode?
+    pub(crate) fn low3_encodable(&self, imm: u8) -> bool {
+        match self {
+            // 0x59 and 0x69 are `scf` and `ccf`
+            Opcode::Op5xHi | Opcode::Op6xHi => imm < 8 && imm != 1,
+            // conditions 0 and 1 are `jnz`/`jnc` and `jz`/`jc`
+            Opcode::JccLo | Opcode::JccHi => (2..8).contains(&imm),
+            _ => imm < 8,
+        }
+    }
+}
+
+/// is `byte` an opcode that some decoder can produce `.byte byte` for?
+///
+/// a [strict](InstDecoder::strict) decoder rejects every byte the default decoder does, as well as
+/// the speculative `op5xhi` and `op6xhi` encodings, so any byte it rejects can be a `.byte`.
+fn undefined_byte(byte: u8) -> bool {
+    use yaxpeax_arch::{Decoder, U8Reader};
+
+    match InstDecoder::strict().decode(&mut U8Reader::new(&[byte])) {
+        Err(e) => e.kind() == DecodeErrorKind::InvalidOpcode,
+        Ok(_) => false,
+    }
+}
+
+impl Instruction {
+    /// build an instruction from an opcode and its operands.
+    ///
+    /// the operands are checked against the shape this opcode is decoded with, and the length of
+    /// the instruction is computed from its encoding. an instruction built this way displays and
+    /// describes itself just as if it had been decoded.
+    ///
+    /// ```
+    /// use yaxpeax_avnera::{Instruction, Opcode, Operand, RegSpec};
+    ///
+    /// let inst = Instruction::build(Opcode::LoadImm8, &[
+    ///     Operand::Register { reg: RegSpec::r4() },
+    ///     Operand::ImmU8 { imm: 0x0e },
+    /// ]).unwrap();
+    ///
+    /// assert_eq!(inst.to_string(), "r4 <- 0x0e");
+    /// assert_eq!(inst.len(), 2);
+    /// ```
+    pub fn build(opcode: Opcode, operands: &[Operand]) -> Result<Instruction, BuildError> {
+        let specs = opcode.operand_specs();
+        if operands.len() != specs.len() {
+            return Err(BuildError::OperandCount { expected: specs.len() as u8 });
+        }
+
+        let mut inst = Instruction::new_0op(opcode);
+        let mut length = 1;
+        for (i, (spec, operand)) in specs.iter().zip(operands.iter()).enumerate() {
+            let index = i as u8;
+            if operand.kind() != spec.kind() {
+                return Err(BuildError::OperandKind { index, expected: spec.kind() });
+            }
+            if let (OperandSpec::ImmLow3, Operand::ImmU8 { imm }) = (spec, operand) {
+                if !opcode.low3_encodable(*imm) {
+                    return Err(BuildError::OperandValue { index });
+                }
+            }
+            if let (OperandSpec::BitLow3, Operand::BitIndex { bit }) = (spec, operand) {
+                if *bit >= 8 {
+                    return Err(BuildError::OperandValue { index });
+                }
+            }
+            if let (Opcode::Undefined, Operand::ImmU8 { imm }) = (opcode, operand) {
+                if !undefined_byte(*imm) {
+                    return Err(BuildError::OperandValue { index });
+                }
+            }
+            inst.operands[i] = *operand;
+            length += spec.trailing_bytes();
+        }
+        inst.operand_count = specs.len() as u8;
+        inst.length = length;
+        if length as u16 > MAX_INSTRUCTION_LEN {
+            return Err(BuildError::TooLong { length });
+        }
+
+        // with operands of the right shapes, encoding can only fail if an extension's opcode byte
+        // does not match it once the low three bits are filled in by an operand.
+        let mut bytes = [0u8; 3];
+        if inst.encode_to(&mut bytes).is_err() {
+            let index = specs.iter().position(|spec| spec.trailing_bytes() == 0).unwrap_or(0);
+            return Err(BuildError::OperandValue { index: index as u8 });
+        }
+        inst.bytes = bytes;
+
+        Ok(inst)
+    }
+}
@@ -1,6 +1,66 @@
+// `Colorize` and `YaxColors` are deprecated as of yaxpeax-arch 0.3, but they are still the
+// colorization interface other yaxpeax crates implement, and what disassemblers use.
+#![allow(deprecated)]
+
 use core::fmt;
 
-use crate::{Instruction, Opcode, Operand};
+use yaxpeax_arch::{Colorize, NoColors, ShowContextual, YaxColors};
+
+use crate::{Instruction, Opcode, RegPair, RegSpec};
+
+impl fmt::Display for RegSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "r{}", self.n)
+    }
+}
+
+impl fmt::Display for RegPair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "r{}:r{}", self.n, self.n + 1)
+    }
+}
+
+/// names for addresses, used to display instructions with [`ShowContextual`].
+///
+/// ```
+/// use yaxpeax_arch::{NoColors, ShowContextual};
+/// use yaxpeax_avnera::InstDecoder;
+///
+/// let symbols: &[(u16, &str)] = &[(0xd98a, "uart_send")];
+/// let inst = InstDecoder::decode_slice(&[0xbf, 0x8a, 0xd9]).unwrap();
+/// let mut out = String::new();
+/// inst.contextualize(&NoColors, 0x1000, Some(symbols), &mut out).unwrap();
+/// assert_eq!(out, "call uart_send");
+/// ```
+pub trait SymbolResolver {
+    /// the name of `addr`, if it has one.
+    fn symbol(&self, addr: u16) -> Option<&str>;
+}
+
+impl SymbolResolver for [(u16, &str)] {
+    fn symbol(&self, addr: u16) -> Option<&str> {
+        self.iter().find(|(sym_addr, _)| *sym_addr == addr).map(|(_, name)| *name)
+    }
+}
+
+/// a resolver with no symbols, for displaying instructions without any context.
+struct NoSymbols;
+
+impl SymbolResolver for NoSymbols {
+    fn symbol(&self, _addr: u16) -> Option<&str> {
+        None
+    }
+}
+
+impl fmt::Debug for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Instruction")
+            .field("opcode", &self.opcode)
+            .field("operands", &&self.operands[..self.operand_count as usize])
+            .field("bytes", &self.bytes())
+            .finish()
+    }
+}
 
 impl fmt::Debug for crate::Operand {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -10,36 +70,45 @@ impl fmt::Debug for crate::Operand {
 
 impl fmt::Display for crate::Operand {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.colorize(&NoColors, f)
+    }
+}
+
+impl<T: fmt::Write, Y: YaxColors> Colorize<T, Y> for crate::Operand {
+    fn colorize(&self, colors: &Y, out: &mut T) -> fmt::Result {
         use crate::Operand::*;
         match self {
             Nothing => { Ok(()) },
-            Register { n } => {
-                write!(f, "r{}", n)
+            Register { reg } => {
+                write!(out, "{}", colors.register(reg))
             }
-            RegisterPair { n } => {
-                write!(f, "r{}:r{}", n, n + 1)
+            RegisterPair { pair } => {
+                write!(out, "{}", colors.register(pair))
             }
             MemAbs16 { addr } => {
-                write!(f, "[0x{:04x}]", addr)
+                write!(out, "[{}]", colors.address(format_args!("0x{:04x}", addr)))
             }
-            MemRegIndirect { n } => {
-                write!(f, "[r{}:r{}]", n, n + 1)
+            MemRegIndirect { pair } => {
+                write!(out, "[{}]", colors.register(pair))
             }
-            MemRegIndirectOffset { n, offs } => {
-                write!(f, "[r{}:r{} + 0x{:x}]", n, n + 1, offs)
+            MemRegIndirectOffset { pair, offs } => {
+                write!(out, "[{} + {}]", colors.register(pair), colors.number(format_args!("0x{:x}", offs)))
             }
             BranchRelI8 { rel } => {
                 if rel < &0 {
-                    write!(f, "$-0x{:x}", rel)
+                    write!(out, "{}{}", colors.program_counter("$"), colors.number(format_args!("-0x{:x}", rel.unsigned_abs())))
                 } else {
-                    write!(f, "$+0x{:x}", rel)
+                    write!(out, "{}{}", colors.program_counter("$"), colors.number(format_args!("+0x{:x}", rel)))
                 }
             }
             ImmU8 { imm } => {
-                write!(f, "0x{:02x}", imm)
+                write!(out, "{}", colors.number(format_args!("0x{:02x}", imm)))
             }
             ImmU16 { imm } => {
-                write!(f, "0x{:04x}", imm)
+                write!(out, "{}", colors.number(format_args!("0x{:04x}", imm)))
+            }
+            BitIndex { bit } => {
+                write!(out, "{}", colors.number(format_args!("0x{:02x}", bit)))
             }
         }
     }
@@ -93,135 +162,287 @@ impl fmt::Display for crate::Opcode {
             StoreRegPair => { f.write_str("storeregpair") },
             LoadRegPairC => { f.write_str("loadregpairc") },
             StoreRegPairC => { f.write_str("storeregpairc") },
+            Extension(ext) => { f.write_str(ext.mnemonic) },
+            Undefined => { f.write_str(".byte") },
+        }
+    }
+}
+
+
+/// `t`, styled as `opcode` would be by `colors`.
+fn opcode_style<'a, Y: YaxColors, D: fmt::Display + 'a>(opcode: Opcode, colors: &'a Y, t: D) -> impl fmt::Display + 'a {
+    use crate::Opcode::*;
+    match opcode {
+        Adc | Or | And | Xor | Rcl | Rcr | Inc | IncW | Dec | Sbc | Add | Bit | Scf | Ccf => {
+            colors.arithmetic_op(t)
         }
+        Cmp => colors.comparison_op(t),
+        MovRnR0 | MovR0Rn | LoadImm8 | LoadAbs16 | StoreAbs16 |
+        LoadRegPair | StoreRegPair | LoadRegPairC | StoreRegPairC => colors.data_op(t),
+        Push | Pop => colors.stack_op(t),
+        Jz | Jc | JccLo | Jnz | Jnc | JccHi | Ret | Iret | Jmp | Call => colors.control_flow_op(t),
+        Op5xHi | Op6xHi | Extension(_) => colors.misc_op(t),
+        Undefined => colors.invalid_op(t),
     }
 }
 
+impl<T: fmt::Write, Y: YaxColors> Colorize<T, Y> for Opcode {
+    fn colorize(&self, colors: &Y, out: &mut T) -> fmt::Result {
+        write!(out, "{}", opcode_style(*self, colors, self))
+    }
+}
 
 impl fmt::Display for Instruction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self.opcode {
-            Opcode::Ret => {
-                f.write_str("ret")
-            },
-            Opcode::Iret => {
-                f.write_str("iret")
-            },
-            Opcode::Jnz => {
-                write!(f, "jnz {}", self.operands[0])
-            },
-            Opcode::Jnc => {
-                write!(f, "jnc {}", self.operands[0])
-            },
-            Opcode::Jz => {
-                write!(f, "jz {}", self.operands[0])
-            },
-            Opcode::Jc => {
-                write!(f, "jc {}", self.operands[0])
-            },
-            Opcode::JccLo => {
-                if let Operand::ImmU8 { imm } = self.operands[0] {
-                    write!(f, "jcc.lo.{:x} {}", imm, self.operands[1])
-                } else {
-                    unreachable!()
-                }
+        self.colorize(&NoColors, f)
+    }
+}
+
+/// what is known about an instruction being displayed, beyond the instruction itself.
+struct Context<'r, R: SymbolResolver + ?Sized> {
+    /// the address of the instruction, for resolving relative branches.
+    address: Option<u16>,
+    /// names to display in place of addresses.
+    symbols: Option<&'r R>,
+    /// the syntax to display the instruction with.
+    style: DisplayStyle,
+}
+
+impl<'r, R: SymbolResolver + ?Sized> Context<'r, R> {
+    fn none() -> Self {
+        Context { address: None, symbols: None, style: DisplayStyle::Pseudocode }
+    }
+}
+
+impl<'r, R: SymbolResolver + ?Sized> Clone for Context<'r, R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'r, R: SymbolResolver + ?Sized> Copy for Context<'r, R> {}
+
+/// the syntax to display instructions with.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Default)]
+pub enum DisplayStyle {
+    /// most instructions written as assignments, like `r0 ^= r1` and `r4 <- 0x0e`. this is how
+    /// instructions `Display` by default.
+    #[default]
+    Pseudocode,
+    /// every instruction written as a mnemonic and comma-separated operands, like `xor r0, r1` and
+    /// `mov r4, 0x0e`, for tools that expect a conventional assembly syntax.
+    Mnemonic,
+}
+
+/// an [`Instruction`] displayed with a particular [`DisplayStyle`] or at a specific address, from
+/// [`Instruction::display_with`] or [`Instruction::display_at`].
+pub struct InstructionDisplay<'a, R: SymbolResolver + ?Sized = dyn SymbolResolver> {
+    inst: &'a Instruction,
+    address: Option<u16>,
+    symbols: Option<&'a R>,
+    style: DisplayStyle,
+}
+
+impl<'a, R: SymbolResolver + ?Sized> InstructionDisplay<'a, R> {
+    /// display the instruction with syntax `style`.
+    pub fn with_style(mut self, style: DisplayStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// display names from `symbols` in place of the addresses of absolute operands and, if the
+    /// instruction's address is known, branch targets.
+    ///
+    /// ```
+    /// use yaxpeax_avnera::{DisplayStyle, InstDecoder};
+    ///
+    /// let symbols: &[(u16, &str)] = &[(0xd98a, "uart_send"), (0x1286, "retry")];
+    /// let call = InstDecoder::decode_slice(&[0xbf, 0x8a, 0xd9]).unwrap();
+    /// assert_eq!(call.display_with(DisplayStyle::Mnemonic).with_symbols(symbols).to_string(), "call uart_send");
+    /// let jnz = InstDecoder::decode_slice(&[0x90, 0x50]).unwrap();
+    /// assert_eq!(jnz.display_at(0x1234).with_symbols(symbols).to_string(), "jnz retry");
+    /// ```
+    pub fn with_symbols<S: SymbolResolver + ?Sized>(self, symbols: &'a S) -> InstructionDisplay<'a, S> {
+        InstructionDisplay { inst: self.inst, address: self.address, symbols: Some(symbols), style: self.style }
+    }
+}
+
+impl Instruction {
+    /// display this instruction with syntax `style`.
+    ///
+    /// ```
+    /// use yaxpeax_avnera::{DisplayStyle, InstDecoder};
+    ///
+    /// let inst = InstDecoder::decode_slice(&[0xe4, 0x0e]).unwrap();
+    /// assert_eq!(inst.to_string(), "r4 <- 0x0e");
+    /// assert_eq!(inst.display_with(DisplayStyle::Mnemonic).to_string(), "mov r4, 0x0e");
+    /// ```
+    pub fn display_with(&self, style: DisplayStyle) -> InstructionDisplay<'_> {
+        InstructionDisplay { inst: self, address: None, symbols: None, style }
+    }
+
+    /// display this instruction as if it were located at `addr`, showing the targets of relative
+    /// branches as absolute addresses.
+    ///
+    /// ```
+    /// use yaxpeax_avnera::InstDecoder;
+    ///
+    /// let inst = InstDecoder::decode_slice(&[0x90, 0x50]).unwrap();
+    /// assert_eq!(inst.to_string(), "jnz $+0x50");
+    /// assert_eq!(inst.display_at(0x1234).to_string(), "jnz 0x1286");
+    /// ```
+    pub fn display_at(&self, addr: u16) -> InstructionDisplay<'_> {
+        InstructionDisplay { inst: self, address: Some(addr), symbols: None, style: DisplayStyle::Pseudocode }
+    }
+}
+
+impl<R: SymbolResolver + ?Sized> fmt::Display for InstructionDisplay<'_, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let context = Context { address: self.address, symbols: self.symbols, style: self.style };
+        self.inst.render(&NoColors, context, f)
+    }
+}
+
+/// the pieces instructions are written with. most `avnera` instructions are written as an
+/// assignment to `r0`, rather than a mnemonic and operands.
+#[derive(Copy, Clone)]
+enum Term {
+    R0,
+    Operand(usize),
+}
+
+impl Instruction {
+    fn colorize_term<T: fmt::Write, Y: YaxColors, R: SymbolResolver + ?Sized>(&self, term: Term, colors: &Y, context: Context<R>, out: &mut T) -> fmt::Result {
+        let op = match term {
+            Term::R0 => { return write!(out, "{}", colors.register(RegSpec::r0())); }
+            Term::Operand(i) => self.operands[i],
+        };
+
+        let target = match op {
+            crate::Operand::MemAbs16 { addr } |
+            crate::Operand::ImmU16 { imm: addr } => Some(addr),
+            crate::Operand::BranchRelI8 { .. } => {
+                context.address.and_then(|addr| self.branch_target(addr))
             }
-            Opcode::JccHi => {
-                if let Operand::ImmU8 { imm } = self.operands[0] {
-                    write!(f, "jcc.hi.{:x} {}", imm, self.operands[1])
+            _ => None,
+        };
+        let symbol = target.and_then(|target| context.symbols?.symbol(target));
+
+        match (op, symbol, target) {
+            (crate::Operand::MemAbs16 { .. }, Some(name), _) => write!(out, "[{}]", colors.symbol(name)),
+            (_, Some(name), _) => write!(out, "{}", colors.symbol(name)),
+            (crate::Operand::BranchRelI8 { .. }, None, Some(target)) => {
+                write!(out, "{}", colors.address(format_args!("0x{:04x}", target)))
+            }
+            _ => op.colorize(colors, out),
+        }
+    }
+
+    /// `mnemonic op, op`
+    fn colorize_mnemonic<T: fmt::Write, Y: YaxColors, R: SymbolResolver + ?Sized>(&self, mnemonic: &str, terms: &[Term], colors: &Y, context: Context<R>, out: &mut T) -> fmt::Result {
+        write!(out, "{}", opcode_style(self.opcode, colors, mnemonic))?;
+        for (i, term) in terms.iter().enumerate() {
+            out.write_str(if i == 0 { " " } else { ", " })?;
+            self.colorize_term(*term, colors, context, out)?;
+        }
+        Ok(())
+    }
+
+    /// `dest op src`, or `mnemonic dest, src` in [`DisplayStyle::Mnemonic`]
+    fn colorize_assign<T: fmt::Write, Y: YaxColors, R: SymbolResolver + ?Sized>(&self, dest: Term, op: &str, src: Term, colors: &Y, context: Context<R>, out: &mut T) -> fmt::Result {
+        if context.style == DisplayStyle::Mnemonic {
+            let mnemonic = match op {
+                "|=" => "or",
+                "&=" => "and",
+                "^=" => "xor",
+                "+=" => "add",
+                "<-" => "mov",
+                _ => op,
+            };
+            return self.colorize_mnemonic(mnemonic, &[dest, src], colors, context, out);
+        }
+
+        self.colorize_term(dest, colors, context, out)?;
+        write!(out, " {} ", opcode_style(self.opcode, colors, op))?;
+        self.colorize_term(src, colors, context, out)
+    }
+}
+
+impl<T: fmt::Write, Y: YaxColors> Colorize<T, Y> for Instruction {
+    fn colorize(&self, colors: &Y, out: &mut T) -> fmt::Result {
+        self.render(colors, Context::<NoSymbols>::none(), out)
+    }
+}
+
+/// displays an instruction at an address, substituting names from a [`SymbolResolver`] for the
+/// addresses of absolute operands and branch targets that have one.
+///
+/// this always uses [`DisplayStyle::Pseudocode`]; for other styles, use
+/// [`Instruction::display_at`] with [`InstructionDisplay::with_style`] and
+/// [`InstructionDisplay::with_symbols`].
+impl<T: fmt::Write, Y: YaxColors, R: SymbolResolver + ?Sized> ShowContextual<u16, R, T, Y> for Instruction {
+    fn contextualize(&self, colors: &Y, address: u16, context: Option<&R>, out: &mut T) -> fmt::Result {
+        self.render(colors, Context { address: Some(address), symbols: context, style: DisplayStyle::Pseudocode }, out)
+    }
+}
+
+impl Instruction {
+    /// write this instruction, with whatever `context` is known about where it is.
+    fn render<T: fmt::Write, Y: YaxColors, R: SymbolResolver + ?Sized>(&self, colors: &Y, context: Context<R>, out: &mut T) -> fmt::Result {
+        use self::Term::*;
+
+        let op0 = Operand(0);
+        let op1 = Operand(1);
+        match self.opcode {
+            Opcode::Ret => self.colorize_mnemonic("ret", &[], colors, context, out),
+            Opcode::Iret => self.colorize_mnemonic("iret", &[], colors, context, out),
+            Opcode::Scf => self.colorize_mnemonic("scf", &[], colors, context, out),
+            Opcode::Ccf => self.colorize_mnemonic("ccf", &[], colors, context, out),
+            Opcode::Jnz => self.colorize_mnemonic("jnz", &[op0], colors, context, out),
+            Opcode::Jnc => self.colorize_mnemonic("jnc", &[op0], colors, context, out),
+            Opcode::Jz => self.colorize_mnemonic("jz", &[op0], colors, context, out),
+            Opcode::Jc => self.colorize_mnemonic("jc", &[op0], colors, context, out),
+            Opcode::JccLo | Opcode::JccHi => {
+                let half = if self.opcode == Opcode::JccLo { "lo" } else { "hi" };
+                if let crate::Operand::ImmU8 { imm } = self.operands[0] {
+                    write!(out, "{} ", opcode_style(self.opcode, colors, format_args!("jcc.{}.{:x}", half, imm)))?;
+                    self.colorize_term(op1, colors, context, out)
                 } else {
                     unreachable!()
                 }
             }
-            Opcode::Adc => {
-                write!(f, "adc r0, {}", self.operands[0])
-            },
-            Opcode::MovRnR0 => {
-                write!(f, "r0 <- {}", self.operands[0])
-            },
-            Opcode::Or => {
-                write!(f, "r0 |= {}", self.operands[0])
-            },
-            Opcode::And => {
-                write!(f, "r0 &= {}", self.operands[0])
-            },
-            Opcode::Xor => {
-                write!(f, "r0 ^= {}", self.operands[0])
-            },
-            Opcode::Rcl => {
-                write!(f, "rcl {}", self.operands[0])
-            },
-            Opcode::Rcr => {
-                write!(f, "rcr {}", self.operands[0])
-            },
-            Opcode::Inc => {
-                write!(f, "inc {}", self.operands[0])
-            },
-            Opcode::IncW => {
-                write!(f, "incw {}", self.operands[0])
-            },
-            Opcode::Dec => {
-                write!(f, "dec {}", self.operands[0])
-            },
-            Opcode::Sbc => {
-                write!(f, "sbc r0, {}", self.operands[0])
-            },
-            Opcode::Add => {
-                write!(f, "r0 += {}", self.operands[0])
-            },
-            Opcode::Op5xHi => {
-                write!(f, "op5xhi {}", self.operands[0])
-            },
-            Opcode::Scf => {
-                write!(f, "scf")
-            },
-            Opcode::Ccf => {
-                write!(f, "ccf")
-            },
-            Opcode::Bit => {
-                write!(f, "bit r0, {}", self.operands[0])
-            },
-            Opcode::Op6xHi => {
-                write!(f, "op6xhi {}", self.operands[0])
-            },
-            Opcode::MovR0Rn => {
-                write!(f, "{} <- r0", self.operands[0])
-            },
-            Opcode::Cmp => {
-                write!(f, "cmp r0, {}", self.operands[0])
-            },
-            Opcode::Push => {
-                write!(f, "push {}", self.operands[0])
-            },
-            Opcode::Pop => {
-                write!(f, "pop {}", self.operands[0])
-            },
-            Opcode::Jmp => {
-                write!(f, "jmp {}", self.operands[0])
-            },
-            Opcode::Call => {
-                write!(f, "call {}", self.operands[0])
-            },
-            Opcode::LoadImm8 => {
-                write!(f, "{} <- {}", self.operands[0], self.operands[1])
-            }
-            Opcode::LoadAbs16 => {
-                write!(f, "{} <- {}", self.operands[0], self.operands[1])
-            }
-            Opcode::StoreAbs16 => {
-                write!(f, "{} <- {}", self.operands[1], self.operands[0])
-            }
-            Opcode::LoadRegPair => {
-                write!(f, "r0 <- {}", self.operands[0])
-            }
-            Opcode::StoreRegPair => {
-                write!(f, "{} <- r0", self.operands[0])
-            }
-            Opcode::LoadRegPairC => {
-                write!(f, "r0 <- {}", self.operands[0])
-            }
-            Opcode::StoreRegPairC => {
-                write!(f, "{} <- r0", self.operands[0])
+            Opcode::Adc => self.colorize_mnemonic("adc", &[R0, op0], colors, context, out),
+            Opcode::Sbc => self.colorize_mnemonic("sbc", &[R0, op0], colors, context, out),
+            Opcode::Bit => self.colorize_mnemonic("bit", &[R0, op0], colors, context, out),
+            Opcode::Cmp => self.colorize_mnemonic("cmp", &[R0, op0], colors, context, out),
+            Opcode::Or => self.colorize_assign(R0, "|=", op0, colors, context, out),
+            Opcode::And => self.colorize_assign(R0, "&=", op0, colors, context, out),
+            Opcode::Xor => self.colorize_assign(R0, "^=", op0, colors, context, out),
+            Opcode::Add => self.colorize_assign(R0, "+=", op0, colors, context, out),
+            Opcode::Rcl => self.colorize_mnemonic("rcl", &[op0], colors, context, out),
+            Opcode::Rcr => self.colorize_mnemonic("rcr", &[op0], colors, context, out),
+            Opcode::Inc => self.colorize_mnemonic("inc", &[op0], colors, context, out),
+            Opcode::IncW => self.colorize_mnemonic("incw", &[op0], colors, context, out),
+            Opcode::Dec => self.colorize_mnemonic("dec", &[op0], colors, context, out),
+            Opcode::Op5xHi => self.colorize_mnemonic("op5xhi", &[op0], colors, context, out),
+            Opcode::Op6xHi => self.colorize_mnemonic("op6xhi", &[op0], colors, context, out),
+            Opcode::Push => self.colorize_mnemonic("push", &[op0], colors, context, out),
+            Opcode::Pop => self.colorize_mnemonic("pop", &[op0], colors, context, out),
+            Opcode::Jmp => self.colorize_mnemonic("jmp", &[op0], colors, context, out),
+            Opcode::Call => self.colorize_mnemonic("call", &[op0], colors, context, out),
+            Opcode::Undefined => self.colorize_mnemonic(".byte", &[op0], colors, context, out),
+            Opcode::MovRnR0 |
+            Opcode::LoadRegPair |
+            Opcode::LoadRegPairC => self.colorize_assign(R0, "<-", op0, colors, context, out),
+            Opcode::MovR0Rn |
+            Opcode::StoreRegPair |
+            Opcode::StoreRegPairC => self.colorize_assign(op0, "<-", R0, colors, context, out),
+            Opcode::LoadImm8 |
+            Opcode::LoadAbs16 => self.colorize_assign(op0, "<-", op1, colors, context, out),
+            Opcode::StoreAbs16 => self.colorize_assign(op1, "<-", op0, colors, context, out),
+            Opcode::Extension(ext) => {
+                let terms = [op0, op1];
+                self.colorize_mnemonic(ext.mnemonic, &terms[..self.operand_count as usize], colors, context, out)
             }
         }
     }
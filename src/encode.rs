@@ -0,0 +1,168 @@
+use core::fmt;
+
+use crate::{Instruction, Opcode, Operand, OperandSpec};
+
+/// the reason [`Instruction::encode_to`] could not encode an instruction.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum EncodeError {
+    /// the output buffer is shorter than the `needed` bytes of this instruction's encoding.
+    BufferTooSmall { needed: u8 },
+    /// there is no encoding for this combination of opcode and operands.
+    NoEncoding,
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EncodeError::BufferTooSmall { needed } => {
+                write!(f, "buffer too small, {} bytes needed", needed)
+            }
+            EncodeError::NoEncoding => {
+                f.write_str("no encoding for opcode and operands")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeError {}
+
+impl Opcode {
+    /// the opcode byte for this opcode, with the low three bits clear where they select an
+    /// operand.
+    fn base_byte(&self) -> u8 {
+        use Opcode::*;
+
+        match self {
+            Inc => 0x00,
+            Adc => 0x08,
+            MovRnR0 => 0x10,
+            Or => 0x18,
+            And => 0x20,
+            Xor => 0x28,
+            Rcl => 0x30,
+            Rcr => 0x38,
+            Dec => 0x40,
+            Sbc => 0x48,
+            Add => 0x50,
+            Op5xHi => 0x58,
+            Scf => 0x59,
+            Bit => 0x60,
+            Op6xHi => 0x68,
+            Ccf => 0x69,
+            MovR0Rn => 0x70,
+            Cmp => 0x78,
+            Push => 0x80,
+            Pop => 0x88,
+            Jnz => 0x90,
+            Jnc => 0x91,
+            JccLo => 0x90,
+            Jz => 0x98,
+            Jc => 0x99,
+            JccHi => 0x98,
+            Ret => 0xb9,
+            Iret => 0xba,
+            Jmp => 0xbc,
+            Call => 0xbf,
+            IncW => 0xc0,
+            StoreAbs16 => 0xc8,
+            StoreRegPair => 0xd0,
+            StoreRegPairC => 0xd8,
+            LoadImm8 => 0xe0,
+            LoadAbs16 => 0xe8,
+            LoadRegPair => 0xf0,
+            LoadRegPairC => 0xf8,
+            Extension(ext) => ext.pattern,
+            Undefined => 0x00,
+        }
+    }
+}
+
+impl Instruction {
+    /// write the encoding of this instruction to the start of `buf`, returning the number of bytes
+    /// written.
+    ///
+    /// this is the inverse of decoding: decoding the written bytes with the same decoder produces
+    /// this instruction again. the exception is instructions decoded by an
+    /// [`crate::OpcodeExtension`]; they are encoded with a byte that matches the extension, but an
+    /// earlier extension given to the same decoder may match that byte too.
+    ///
+    /// ```
+    /// use yaxpeax_avnera::InstDecoder;
+    ///
+    /// let inst = InstDecoder::decode_slice(&[0xc9, 0xf2, 0xed]).unwrap();
+    /// let mut buf = [0u8; 4];
+    /// assert_eq!(inst.encode_to(&mut buf), Ok(3));
+    /// assert_eq!(&buf[..3], &[0xc9, 0xf2, 0xed]);
+    /// ```
+    pub fn encode_to(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        let specs = self.opcode.operand_specs();
+        if specs.len() != self.operand_count as usize {
+            return Err(EncodeError::NoEncoding);
+        }
+
+        let length = 1 + specs.iter().map(|spec| spec.trailing_bytes() as usize).sum::<usize>();
+        if buf.len() < length {
+            return Err(EncodeError::BufferTooSmall { needed: length as u8 });
+        }
+
+        let mut opcode_byte = self.opcode.base_byte();
+        let mut offset = 1;
+        for (spec, operand) in specs.iter().zip(self.operands.iter()) {
+            let low3 = match (spec, operand) {
+                (OperandSpec::RegisterLow3, Operand::Register { reg }) => Some(reg.num()),
+                (OperandSpec::RegisterPairLow3, Operand::RegisterPair { pair }) |
+                (OperandSpec::MemRegIndirectLow3, Operand::MemRegIndirect { pair }) => Some(pair.num()),
+                (OperandSpec::MemRegIndirectOffsetLow3, Operand::MemRegIndirectOffset { pair, offs }) => {
+                    buf[offset] = *offs;
+                    Some(pair.num())
+                }
+                (OperandSpec::ImmLow3, Operand::ImmU8 { imm }) => {
+                    if !self.opcode.low3_encodable(*imm) {
+                        return Err(EncodeError::NoEncoding);
+                    }
+                    Some(*imm)
+                }
+                (OperandSpec::BitLow3, Operand::BitIndex { bit }) => {
+                    if *bit >= 8 {
+                        return Err(EncodeError::NoEncoding);
+                    }
+                    Some(*bit)
+                }
+                (OperandSpec::OpcodeByte, Operand::ImmU8 { imm }) => {
+                    opcode_byte = *imm;
+                    None
+                }
+                (OperandSpec::ImmU8, Operand::ImmU8 { imm }) => {
+                    buf[offset] = *imm;
+                    None
+                }
+                (OperandSpec::BranchRelI8, Operand::BranchRelI8 { rel }) => {
+                    buf[offset] = *rel as u8;
+                    None
+                }
+                (OperandSpec::ImmU16, Operand::ImmU16 { imm: value }) |
+                (OperandSpec::MemAbs16, Operand::MemAbs16 { addr: value }) => {
+                    buf[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+                    None
+                }
+                _ => {
+                    return Err(EncodeError::NoEncoding);
+                }
+            };
+            if let Some(low3) = low3 {
+                opcode_byte |= low3;
+            }
+            offset += spec.trailing_bytes() as usize;
+        }
+
+        if let Opcode::Extension(ext) = self.opcode {
+            if !ext.matches(opcode_byte) {
+                return Err(EncodeError::NoEncoding);
+            }
+        }
+
+        buf[0] = opcode_byte;
+        Ok(length)
+    }
+}
@@ -0,0 +1,106 @@
+use core::fmt;
+
+use yaxpeax_arch::ReadError;
+
+/// why decoding an instruction failed.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum DecodeErrorKind {
+    /// the input ended before the end of the instruction.
+    ExhaustedInput,
+    /// the opcode byte does not decode to any known instruction.
+    InvalidOpcode,
+}
+
+/// an error from decoding an `avnera` instruction.
+///
+/// in addition to the kind of failure, this records the opcode byte of the instruction that failed
+/// to decode, if there was one, and the offset into the instruction where decoding failed. for an
+/// invalid opcode the offset is always 0, the opcode byte itself; for truncated input it is the
+/// offset of the first byte that could not be read.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct DecodeError {
+    kind: DecodeErrorKind,
+    opcode: Option<u8>,
+    offset: u8,
+}
+
+impl DecodeError {
+    pub(crate) fn invalid_opcode() -> Self {
+        DecodeError {
+            kind: DecodeErrorKind::InvalidOpcode,
+            opcode: None,
+            offset: 0,
+        }
+    }
+
+    /// fill in where this error occurred, given the bytes of the instruction read before decoding
+    /// failed.
+    pub(crate) fn with_context(mut self, bytes_read: &[u8]) -> Self {
+        self.opcode = bytes_read.first().cloned();
+        self.offset = match self.kind {
+            DecodeErrorKind::ExhaustedInput => bytes_read.len() as u8,
+            DecodeErrorKind::InvalidOpcode => 0,
+        };
+        self
+    }
+
+    /// the kind of failure this error describes.
+    pub fn kind(&self) -> DecodeErrorKind {
+        self.kind
+    }
+
+    /// the opcode byte of the instruction that failed to decode, or `None` if the input ended
+    /// before there was an opcode byte.
+    pub fn opcode(&self) -> Option<u8> {
+        self.opcode
+    }
+
+    /// the offset, in bytes from the start of the instruction, where decoding failed.
+    pub fn offset(&self) -> u8 {
+        self.offset
+    }
+}
+
+impl From<ReadError> for DecodeError {
+    fn from(_: ReadError) -> DecodeError {
+        DecodeError {
+            kind: DecodeErrorKind::ExhaustedInput,
+            opcode: None,
+            offset: 0,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.kind, self.opcode) {
+            (DecodeErrorKind::InvalidOpcode, Some(opcode)) => {
+                write!(f, "invalid opcode 0x{:02x}", opcode)
+            }
+            (DecodeErrorKind::InvalidOpcode, None) => {
+                f.write_str("invalid opcode")
+            }
+            (DecodeErrorKind::ExhaustedInput, Some(opcode)) => {
+                write!(f, "exhausted input at byte {} of instruction with opcode 0x{:02x}", self.offset, opcode)
+            }
+            (DecodeErrorKind::ExhaustedInput, None) => {
+                f.write_str("exhausted input")
+            }
+        }
+    }
+}
+
+impl yaxpeax_arch::DecodeError for DecodeError {
+    fn data_exhausted(&self) -> bool { self.kind == DecodeErrorKind::ExhaustedInput }
+    fn bad_opcode(&self) -> bool { self.kind == DecodeErrorKind::InvalidOpcode }
+    fn bad_operand(&self) -> bool { false }
+    fn description(&self) -> &'static str {
+        match self.kind {
+            DecodeErrorKind::ExhaustedInput => "exhausted input",
+            DecodeErrorKind::InvalidOpcode => "invalid opcode",
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
@@ -0,0 +1,268 @@
+use yaxpeax_arch::{Arch, Reader, U8Reader};
+
+use crate::{Avnera, DecodeError, Instruction, Opcode, Operand, OperandKind, RegPair, RegSpec};
+
+/// where an operand of an [`OpcodeExtension`] comes from.
+///
+/// the `*Low3` shapes take a value from the low three bits of the opcode byte, as most `avnera`
+/// instructions select a register this way. the other shapes consume bytes following the opcode,
+/// in the order the operands are listed.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OperandSpec {
+    /// no operand.
+    Nothing,
+    /// `Operand::Register` selected by the low three bits of the opcode.
+    RegisterLow3,
+    /// `Operand::RegisterPair` selected by the low three bits of the opcode.
+    RegisterPairLow3,
+    /// `Operand::MemRegIndirect` selected by the low three bits of the opcode.
+    MemRegIndirectLow3,
+    /// `Operand::MemRegIndirectOffset` selected by the low three bits of the opcode, with the
+    /// offset in the next byte.
+    MemRegIndirectOffsetLow3,
+    /// `Operand::ImmU8` of the low three bits of the opcode.
+    ImmLow3,
+    /// `Operand::BitIndex` of the low three bits of the opcode.
+    BitLow3,
+    /// `Operand::ImmU8` from the next byte.
+    ImmU8,
+    /// `Operand::ImmU16` from the next two bytes, little-endian.
+    ImmU16,
+    /// `Operand::MemAbs16` from the next two bytes, little-endian.
+    MemAbs16,
+    /// `Operand::BranchRelI8` from the next byte.
+    BranchRelI8,
+    /// `Operand::ImmU8` of the entire opcode byte.
+    OpcodeByte,
+}
+
+/// an experimental opcode definition, for trying out interpretations of bytes that this crate
+/// does not otherwise decode.
+///
+/// an opcode byte `b` matches this extension if `b & mask == pattern`. extensions are consulted,
+/// in order, only for bytes that would otherwise be an invalid opcode.
+///
+/// ```
+/// use yaxpeax_arch::{Decoder, U8Reader};
+/// use yaxpeax_avnera::{InstDecoder, OpcodeExtension, OperandSpec};
+///
+/// static EXTENSIONS: [OpcodeExtension; 1] = [
+///     OpcodeExtension {
+///         pattern: 0xa0,
+///         mask: 0xf8,
+///         mnemonic: "xchg",
+///         operands: [OperandSpec::RegisterLow3, OperandSpec::Nothing],
+///         description: "exchange r0 and rN?",
+///     },
+/// ];
+///
+/// let decoder = InstDecoder::default().with_extensions(&EXTENSIONS);
+/// let inst = decoder.decode(&mut U8Reader::new(&[0xa3])).unwrap();
+/// assert_eq!(inst.to_string(), "xchg r3");
+/// assert_eq!(inst.extension().unwrap().description, "exchange r0 and rN?");
+/// ```
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OpcodeExtension {
+    /// the value of the opcode byte's bits selected by `mask`.
+    pub pattern: u8,
+    /// which bits of the opcode byte must match `pattern`.
+    pub mask: u8,
+    /// the mnemonic to display decoded instructions with.
+    pub mnemonic: &'static str,
+    /// the shape of up to two operands. operands after the first `OperandSpec::Nothing` are
+    /// ignored.
+    ///
+    /// no `avnera` instruction is longer than three bytes, so together the operands may take at
+    /// most two bytes after the opcode byte; [`crate::InstDecoder::with_extensions`] rejects extensions
+    /// that take more.
+    pub operands: [OperandSpec; 2],
+    /// a free-form description of the hypothesized behavior, for whoever is reading results.
+    pub description: &'static str,
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl OpcodeExtension {
+    /// does the opcode byte `word` select this extension?
+    pub fn matches(&self, word: u8) -> bool {
+        word & self.mask == self.pattern
+    }
+
+    /// the length, in bytes, of instructions decoded by this extension.
+    pub fn len(&self) -> u8 {
+        1 + trailing_bytes(&self.operands)
+    }
+}
+
+/// how many bytes after the opcode byte `specs` are encoded in, up to the first
+/// `OperandSpec::Nothing`.
+pub(crate) fn trailing_bytes(specs: &[OperandSpec]) -> u8 {
+    specs.iter()
+        .take_while(|spec| **spec != OperandSpec::Nothing)
+        .map(|spec| spec.trailing_bytes())
+        .sum()
+}
+
+/// a hypothesized interpretation of some opcode bytes, decoded in place of whatever this crate
+/// would otherwise decode them as.
+///
+/// an opcode byte `b` matches this override if `b & mask == pattern`. unlike [`OpcodeExtension`],
+/// overrides are consulted, in order, before any other decoding, so they can replace the
+/// interpretation of bytes this crate already decodes; the speculative `op5xhi` and `op6xhi`
+/// ranges are the obvious candidates. the decoded instruction has `opcode` and operands decoded
+/// as `operands` describes, so it can reuse an existing opcode's semantics or, with
+/// `Opcode::Extension`, describe something new.
+///
+/// [`Instruction::encode_to`](crate::Instruction::encode_to) knows nothing of overrides, and
+/// encodes an overridden instruction as `opcode` normally would be, if it can.
+///
+/// ```
+/// use yaxpeax_arch::{Decoder, U8Reader};
+/// use yaxpeax_avnera::{InstDecoder, Opcode, OpcodeOverride, OperandSpec};
+///
+/// // what if `0x5a..0x5f` were really `bit` with a bit index?
+/// static OVERRIDES: [OpcodeOverride; 1] = [
+///     OpcodeOverride {
+///         pattern: 0x58,
+///         mask: 0xf8,
+///         opcode: Opcode::Bit,
+///         operands: [OperandSpec::BitLow3, OperandSpec::Nothing],
+///     },
+/// ];
+///
+/// let decoder = InstDecoder::default().with_overrides(&OVERRIDES);
+/// let inst = decoder.decode(&mut U8Reader::new(&[0x5a])).unwrap();
+/// assert_eq!(inst.to_string(), "bit r0, 0x02");
+/// ```
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OpcodeOverride {
+    /// the value of the opcode byte's bits selected by `mask`.
+    pub pattern: u8,
+    /// which bits of the opcode byte must match `pattern`.
+    pub mask: u8,
+    /// the opcode to decode matching bytes as.
+    pub opcode: Opcode,
+    /// the shape of up to two operands. operands after the first `OperandSpec::Nothing` are
+    /// ignored.
+    ///
+    /// these must be the same operands `opcode` is normally decoded with, and every opcode byte
+    /// this override matches must decode to operands that
+    /// [`Instruction::build`](crate::Instruction::build) accepts for `opcode`; `jcc.lo` can not
+    /// take a condition of `0` or `1`, as those are `jnz` and `jnc`. as with [`OpcodeExtension`],
+    /// the operands may take at most two bytes after the opcode byte.
+    /// [`crate::InstDecoder::with_overrides`] rejects overrides that do not meet these
+    /// requirements.
+    pub operands: [OperandSpec; 2],
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl OpcodeOverride {
+    /// does the opcode byte `word` select this override?
+    pub fn matches(&self, word: u8) -> bool {
+        word & self.mask == self.pattern
+    }
+
+    /// the length, in bytes, of instructions decoded by this override.
+    pub fn len(&self) -> u8 {
+        1 + trailing_bytes(&self.operands)
+    }
+
+    /// does every opcode byte this override matches decode to an instruction that could have been
+    /// built with `opcode`?
+    pub(crate) fn conforms_to_opcode(&self) -> bool {
+        let count = self.operands.iter()
+            .position(|spec| *spec == OperandSpec::Nothing)
+            .unwrap_or(self.operands.len());
+        if self.operands[..count] != *self.opcode.operand_specs() {
+            return false;
+        }
+
+        // bytes after the opcode byte are never out of range, so zeroes stand in for all of them.
+        (0..=0xffu8).filter(|word| self.matches(*word)).all(|word| {
+            match decode_with_specs(self.opcode, &self.operands, word, &mut U8Reader::new(&[0, 0])) {
+                Ok(inst) => Instruction::build(self.opcode, &inst.operands[..inst.operand_count as usize]).is_ok(),
+                Err(_) => false,
+            }
+        })
+    }
+}
+
+impl OperandSpec {
+    /// the kind of operand this spec decodes to.
+    pub fn kind(&self) -> OperandKind {
+        match self {
+            OperandSpec::Nothing => OperandKind::Nothing,
+            OperandSpec::RegisterLow3 => OperandKind::Register,
+            OperandSpec::RegisterPairLow3 => OperandKind::RegisterPair,
+            OperandSpec::MemRegIndirectLow3 => OperandKind::MemRegIndirect,
+            OperandSpec::MemRegIndirectOffsetLow3 => OperandKind::MemRegIndirectOffset,
+            OperandSpec::ImmLow3 | OperandSpec::ImmU8 | OperandSpec::OpcodeByte => OperandKind::ImmU8,
+            OperandSpec::ImmU16 => OperandKind::ImmU16,
+            OperandSpec::MemAbs16 => OperandKind::MemAbs16,
+            OperandSpec::BranchRelI8 => OperandKind::BranchRelI8,
+            OperandSpec::BitLow3 => OperandKind::BitIndex,
+        }
+    }
+
+    /// how many bytes after the opcode byte this operand is encoded in.
+    pub fn trailing_bytes(&self) -> u8 {
+        match self {
+            OperandSpec::Nothing |
+            OperandSpec::RegisterLow3 |
+            OperandSpec::RegisterPairLow3 |
+            OperandSpec::MemRegIndirectLow3 |
+            OperandSpec::ImmLow3 |
+            OperandSpec::BitLow3 |
+            OperandSpec::OpcodeByte => 0,
+            OperandSpec::MemRegIndirectOffsetLow3 |
+            OperandSpec::ImmU8 |
+            OperandSpec::BranchRelI8 => 1,
+            OperandSpec::ImmU16 |
+            OperandSpec::MemAbs16 => 2,
+        }
+    }
+
+    pub(crate) fn decode<T: Reader<<Avnera as Arch>::Address, <Avnera as Arch>::Word>>(&self, word: u8, words: &mut T) -> Result<Operand, <Avnera as Arch>::DecodeError> {
+        let low_bits = word & 0b111;
+        Ok(match self {
+            OperandSpec::Nothing => Operand::Nothing,
+            OperandSpec::RegisterLow3 => Operand::Register { reg: RegSpec { n: low_bits } },
+            OperandSpec::RegisterPairLow3 => Operand::RegisterPair { pair: RegPair { n: low_bits } },
+            OperandSpec::MemRegIndirectLow3 => Operand::MemRegIndirect { pair: RegPair { n: low_bits } },
+            OperandSpec::MemRegIndirectOffsetLow3 => Operand::MemRegIndirectOffset { pair: RegPair { n: low_bits }, offs: words.next()? },
+            OperandSpec::ImmLow3 => Operand::ImmU8 { imm: low_bits },
+            OperandSpec::BitLow3 => Operand::BitIndex { bit: low_bits },
+            OperandSpec::ImmU8 => Operand::ImmU8 { imm: words.next()? },
+            OperandSpec::ImmU16 => Operand::ImmU16 { imm: crate::next_u16(words)? },
+            OperandSpec::MemAbs16 => Operand::MemAbs16 { addr: crate::next_u16(words)? },
+            OperandSpec::BranchRelI8 => Operand::BranchRelI8 { rel: words.next()? as i8 },
+            OperandSpec::OpcodeByte => Operand::ImmU8 { imm: word },
+        })
+    }
+}
+
+/// decode `word`, and any following bytes, with the first of `extensions` that matches.
+pub(crate) fn decode_extension<T: Reader<<Avnera as Arch>::Address, <Avnera as Arch>::Word>>(extensions: &'static [OpcodeExtension], word: u8, words: &mut T) -> Result<Instruction, <Avnera as Arch>::DecodeError> {
+    let ext = extensions.iter().find(|ext| ext.matches(word))
+        .ok_or_else(DecodeError::invalid_opcode)?;
+
+    decode_with_specs(Opcode::Extension(ext), &ext.operands, word, words)
+}
+
+/// decode `word`, and any following bytes, with the first of `overrides` that matches, or `None`
+/// if none do.
+pub(crate) fn decode_override<T: Reader<<Avnera as Arch>::Address, <Avnera as Arch>::Word>>(overrides: &'static [OpcodeOverride], word: u8, words: &mut T) -> Option<Result<Instruction, <Avnera as Arch>::DecodeError>> {
+    overrides.iter().find(|ov| ov.matches(word))
+        .map(|ov| decode_with_specs(ov.opcode, &ov.operands, word, words))
+}
+
+fn decode_with_specs<T: Reader<<Avnera as Arch>::Address, <Avnera as Arch>::Word>>(opcode: Opcode, specs: &[OperandSpec; 2], word: u8, words: &mut T) -> Result<Instruction, <Avnera as Arch>::DecodeError> {
+    let mut inst = Instruction::new_0op(opcode);
+    for spec in specs.iter() {
+        if *spec == OperandSpec::Nothing {
+            break;
+        }
+        inst.operands[inst.operand_count as usize] = spec.decode(word, words)?;
+        inst.operand_count += 1;
+    }
+    Ok(inst)
+}
@@ -35,14 +35,14 @@
 //! opcodes and operands are available on the decoded instruction, as well as its length and
 //! operand count:
 //! ```
-//! use yaxpeax_avnera::{InstDecoder, Operand};
+//! use yaxpeax_avnera::{InstDecoder, Operand, RegSpec};
 //!
 //! let inst = InstDecoder::decode_slice(&[0x28]).unwrap();
 //!
 //! assert_eq!("r0 ^= r0", inst.to_string());
 //! assert_eq!(inst.operand_count(), 1);
 //! assert_eq!(inst.len(), 1);
-//! assert_eq!(inst.operand(0).unwrap(), Operand::Register { n: 0 });
+//! assert_eq!(inst.operand(0).unwrap(), Operand::Register { reg: RegSpec::r0() });
 //! ```
 //!
 //! additionally, `yaxpeax-avnera` implements `yaxpeax-arch` traits for generic use, such as
@@ -51,13 +51,39 @@
 //!
 //! ## `#![no_std]`
 //!
-//! `yaxpeax-avnera` should support `no_std` usage, but this is entirely untested.
+//! `yaxpeax-avnera` should support `no_std` usage, but this is entirely untested. the `std`
+//! feature is enabled by default, only to implement `std::error::Error` for [`DecodeError`];
+//! disable default features to build without `std`.
 
 #![no_std]
+#![forbid(unsafe_code)]
 
+#[cfg(feature = "std")]
+extern crate std;
+
+mod addr;
+mod annotate;
+mod boundaries;
+mod build;
 mod display;
+mod encode;
+mod error;
+mod extension;
+mod part;
+mod semantics;
+
+pub use addr::Addr16;
+pub use annotate::FieldDescription;
+pub use boundaries::InstructionBoundaries;
+pub use build::BuildError;
+pub use display::{DisplayStyle, InstructionDisplay, SymbolResolver};
+pub use encode::EncodeError;
+pub use error::{DecodeError, DecodeErrorKind};
+pub use extension::{OpcodeExtension, OpcodeOverride, OperandSpec};
+pub use part::Part;
+pub use semantics::{AccessDirection, BitAccess, BitOperation, Confidence, FlagEffect, FlagEffects, MemAccess, RegisterSet};
 
-use yaxpeax_arch::{AddressDiff, Arch, Decoder, LengthedInstruction, Reader, StandardDecodeError};
+use yaxpeax_arch::{AddressDiff, Arch, Decoder, LengthedInstruction, Reader};
 
 /// a trivial struct for [`yaxpeax_arch::Arch`] to be implemented on. it's only interesting for the
 /// associated type parameters.
@@ -69,7 +95,7 @@ impl Arch for Avnera {
     type Word = u8;
     type Instruction = Instruction;
     type Decoder = InstDecoder;
-    type DecodeError = StandardDecodeError;
+    type DecodeError = DecodeError;
     type Operand = Operand;
 }
 
@@ -81,12 +107,39 @@ impl Arch for Avnera {
 /// it seems that instructions for Avnera processors specify at most one explicit register (or
 /// register pair), and may also specify an immediate memory address. with implicit registers
 /// included there seem to only be zero, one, or two operands to an instruction.
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+///
+/// with the `serde` feature, deserialized instructions are checked with [`Instruction::build`],
+/// and their length and bytes are recomputed from the opcode and operands.
+#[derive(Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "SerializedInstruction"))]
 pub struct Instruction {
     opcode: Opcode,
     operands: [Operand; 2],
     operand_count: u8,
     length: u8,
+    bytes: [u8; 3],
+}
+
+/// an [`Instruction`] as it is serialized, before its opcode and operands have been checked for
+/// consistency.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SerializedInstruction {
+    opcode: Opcode,
+    operands: [Operand; 2],
+    operand_count: u8,
+}
+
+#[cfg(feature = "serde")]
+impl core::convert::TryFrom<SerializedInstruction> for Instruction {
+    type Error = BuildError;
+
+    fn try_from(inst: SerializedInstruction) -> Result<Instruction, BuildError> {
+        let operands = inst.operands.get(..inst.operand_count as usize)
+            .ok_or(BuildError::OperandCount { expected: inst.opcode.operand_specs().len() as u8 })?;
+        Instruction::build(inst.opcode, operands)
+    }
 }
 
 impl Default for Instruction {
@@ -96,10 +149,12 @@ impl Default for Instruction {
             operands: [Operand::Nothing, Operand::Nothing],
             operand_count: 0,
             length: 0,
+            bytes: [0; 3],
         }
     }
 }
 
+#[allow(clippy::len_without_is_empty)]
 impl Instruction {
     fn reset_operands(&mut self) {
         self.operands = [Operand::Nothing, Operand::Nothing];
@@ -110,6 +165,16 @@ impl Instruction {
         self.length
     }
 
+    pub fn opcode(&self) -> Opcode {
+        self.opcode
+    }
+
+    /// the bytes this instruction was decoded from. for an instruction from
+    /// [`Instruction::build`], these are the bytes it encodes to.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.length as usize]
+    }
+
     pub fn operand_count(&self) -> u8 {
         self.operand_count
     }
@@ -117,6 +182,109 @@ impl Instruction {
     pub fn operand(&self, idx: u8) -> Option<Operand> {
         self.operands.get(idx as usize).cloned()
     }
+
+    /// iterate over the operands present in this instruction, in order. this yields exactly
+    /// [`Instruction::operand_count`] operands, never `Operand::Nothing`.
+    pub fn operands(&self) -> impl Iterator<Item = Operand> + '_ {
+        self.operands[..self.operand_count as usize].iter().cloned()
+    }
+
+    /// the user-provided definition this instruction was decoded with, if it was decoded by an
+    /// [`OpcodeExtension`].
+    pub fn extension(&self) -> Option<&'static OpcodeExtension> {
+        if let Opcode::Extension(ext) = self.opcode {
+            Some(ext)
+        } else {
+            None
+        }
+    }
+
+    /// is this instruction a jump, conditional or not? calls and returns are not branches.
+    ///
+    /// an instruction decoded by an [`OpcodeExtension`] is a branch if it has a relative branch
+    /// operand, as in a hypothesized `djnz`.
+    pub fn is_branch(&self) -> bool {
+        matches!(self.opcode,
+            Opcode::Jmp |
+            Opcode::Jz | Opcode::Jc | Opcode::Jnz | Opcode::Jnc |
+            Opcode::JccLo | Opcode::JccHi
+        ) || self.is_extension_branch()
+    }
+
+    /// is this instruction a conditional branch? the condition may be one of the as-yet-unknown
+    /// conditions of `jcc.lo`/`jcc.hi`.
+    ///
+    /// whether an extension's branch is taken is not known, so extension branches are
+    /// conservatively conditional: execution may continue at either the target or the next
+    /// instruction.
+    pub fn is_conditional(&self) -> bool {
+        matches!(self.opcode,
+            Opcode::Jz | Opcode::Jc | Opcode::Jnz | Opcode::Jnc |
+            Opcode::JccLo | Opcode::JccHi
+        ) || self.is_extension_branch()
+    }
+
+    fn is_extension_branch(&self) -> bool {
+        matches!(self.opcode, Opcode::Extension(_)) &&
+            self.operands().any(|op| op.kind() == OperandKind::BranchRelI8)
+    }
+
+    /// is this instruction a call?
+    pub fn is_call(&self) -> bool {
+        self.opcode == Opcode::Call
+    }
+
+    /// is this instruction a return, either from a function or an interrupt handler?
+    pub fn is_return(&self) -> bool {
+        matches!(self.opcode, Opcode::Ret | Opcode::Iret)
+    }
+
+    /// the condition this instruction branches on, if it is a conditional branch.
+    pub fn condition(&self) -> Option<ConditionCode> {
+        match self.opcode {
+            Opcode::Jz => Some(ConditionCode::Z),
+            Opcode::Jnz => Some(ConditionCode::NZ),
+            Opcode::Jc => Some(ConditionCode::C),
+            Opcode::Jnc => Some(ConditionCode::NC),
+            Opcode::JccLo | Opcode::JccHi => {
+                if let Operand::ImmU8 { imm } = self.operands[0] {
+                    let hi = if self.opcode == Opcode::JccHi { 0b1000 } else { 0 };
+                    Some(ConditionCode::Unknown(hi | imm))
+                } else {
+                    unreachable!()
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// the address this instruction transfers control to, if this instruction is located at
+    /// `addr`.
+    ///
+    /// relative branches are resolved against the address of the next instruction, and absolute
+    /// `jmp` and `call` targets are returned as-is. address arithmetic wraps at the ends of the
+    /// 16-bit address space. returns `None` for instructions without a static target, including
+    /// `ret` and `iret`.
+    pub fn branch_target(&self, addr: u16) -> Option<u16> {
+        match self.opcode {
+            Opcode::Jmp | Opcode::Call => {
+                if let Operand::ImmU16 { imm } = self.operands[0] {
+                    Some(imm)
+                } else {
+                    unreachable!()
+                }
+            }
+            _ => {
+                self.operands().find_map(|op| {
+                    if let Operand::BranchRelI8 { rel } = op {
+                        Some(Addr16(addr).wrapping_add(self.length as u16).offset(rel as i16).0)
+                    } else {
+                        None
+                    }
+                })
+            }
+        }
+    }
 }
 
 impl LengthedInstruction for Instruction {
@@ -130,13 +298,120 @@ impl LengthedInstruction for Instruction {
 }
 
 impl yaxpeax_arch::Instruction for Instruction {
-    fn well_defined(&self) -> bool { true }
+    fn well_defined(&self) -> bool { self.semantics_known() }
+}
+
+/// one of the eight registers `r0..r7`.
+///
+/// the only way to get a `RegSpec` is through the constructors here or from a decoded instruction,
+/// so a `RegSpec` always names a register that exists.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "u8", into = "u8"))]
+pub struct RegSpec {
+    n: u8,
+}
+
+impl RegSpec {
+    /// the register `rN`, if `N` is a valid register number (`0..=7`).
+    pub fn from_num(n: u8) -> Option<RegSpec> {
+        if n < 8 {
+            Some(RegSpec { n })
+        } else {
+            None
+        }
+    }
+
+    /// the number `N` of this register `rN`.
+    pub fn num(&self) -> u8 {
+        self.n
+    }
+
+    pub const fn r0() -> RegSpec { RegSpec { n: 0 } }
+    pub const fn r1() -> RegSpec { RegSpec { n: 1 } }
+    pub const fn r2() -> RegSpec { RegSpec { n: 2 } }
+    pub const fn r3() -> RegSpec { RegSpec { n: 3 } }
+    pub const fn r4() -> RegSpec { RegSpec { n: 4 } }
+    pub const fn r5() -> RegSpec { RegSpec { n: 5 } }
+    pub const fn r6() -> RegSpec { RegSpec { n: 6 } }
+    pub const fn r7() -> RegSpec { RegSpec { n: 7 } }
+}
+
+/// a register pair `rN:rN+1`, named by its first register.
+///
+/// programs seem to only use pairs starting at even registers, but encodings for the odd pairs
+/// exist and decode. `r7:r8` in particular is nonsense, but decodes all the same.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "u8", into = "u8"))]
+pub struct RegPair {
+    n: u8,
+}
+
+impl RegPair {
+    /// the pair `rN:rN+1`, if `N` is a valid register number (`0..=7`).
+    pub fn from_base(n: u8) -> Option<RegPair> {
+        if n < 8 {
+            Some(RegPair { n })
+        } else {
+            None
+        }
+    }
+
+    /// the number `N` of the first register in this pair.
+    pub fn num(&self) -> u8 {
+        self.n
+    }
+
+    /// the first register of this pair, `rN`.
+    pub fn first(&self) -> RegSpec {
+        RegSpec { n: self.n }
+    }
+
+    /// the second register of this pair, `rN+1`, or `None` for the pair starting at `r7`.
+    pub fn second(&self) -> Option<RegSpec> {
+        RegSpec::from_num(self.n + 1)
+    }
+
+    pub const fn r0_r1() -> RegPair { RegPair { n: 0 } }
+    pub const fn r2_r3() -> RegPair { RegPair { n: 2 } }
+    pub const fn r4_r5() -> RegPair { RegPair { n: 4 } }
+    pub const fn r6_r7() -> RegPair { RegPair { n: 6 } }
+}
+
+impl core::convert::TryFrom<u8> for RegSpec {
+    type Error = &'static str;
+
+    fn try_from(n: u8) -> Result<RegSpec, Self::Error> {
+        RegSpec::from_num(n).ok_or("register number out of range")
+    }
+}
+
+impl From<RegSpec> for u8 {
+    fn from(reg: RegSpec) -> u8 {
+        reg.n
+    }
+}
+
+impl core::convert::TryFrom<u8> for RegPair {
+    type Error = &'static str;
+
+    fn try_from(n: u8) -> Result<RegPair, Self::Error> {
+        RegPair::from_base(n).ok_or("register number out of range")
+    }
+}
+
+impl From<RegPair> for u8 {
+    fn from(pair: RegPair) -> u8 {
+        pair.n
+    }
 }
 
 /// an operand for an `avnera` instruction. like the instructions themselves, these are not
 /// documented in any way i could find. these operands are best guesses from staring at firmware
 /// binaries really hard.
 #[derive(Copy, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Operand {
     /// no operand in this position.
     ///
@@ -144,22 +419,24 @@ pub enum Operand {
     /// `Instruction::operand` will return `None` rather than `Operand::Nothing`.
     Nothing,
     /// a register, either `r0` (implicit) or one of `r0..r7`
-    Register { n: u8 },
+    Register { reg: RegSpec },
     /// a register pair. seems like theoretically this could be any `rN:rN+1`, especially uncertain
     /// what happens if `N` is 7. realistically programs seem to only pick even N.
     ///
-    /// as an example: `RegisterPair { n: 4 }` describes the reigster pair `r4:r5`.
-    RegisterPair { n: u8 },
+    /// as an example: `RegisterPair { pair: RegPair::r4_r5() }` describes the reigster pair
+    /// `r4:r5`.
+    RegisterPair { pair: RegPair },
     /// a memory access to a 16-bit address.
     MemAbs16 { addr: u16 },
     /// a memory access through a register pair.
     ///
-    /// as an example: `MemRegIndirect { n: 6 }` describes the operand `[r6:r7]`
-    MemRegIndirect { n: u8 },
+    /// as an example: `MemRegIndirect { pair: RegPair::r6_r7() }` describes the operand `[r6:r7]`
+    MemRegIndirect { pair: RegPair },
     /// a memory access through a register pair, with an 8-bit offset.
     ///
-    /// as an example: `MemRegIndirectOffset { n: 4, offs: 0x40 }` describes the operand `[r4:r5 + 0x40]`
-    MemRegIndirectOffset { n: u8, offs: u8 },
+    /// as an example: `MemRegIndirectOffset { pair: RegPair::r4_r5(), offs: 0x40 }` describes the
+    /// operand `[r4:r5 + 0x40]`
+    MemRegIndirectOffset { pair: RegPair, offs: u8 },
     /// a relative branch by `rel`.
     ///
     ///  the actual address branched to for an instruction at address `A` would be `A + inst.len()
@@ -175,14 +452,130 @@ pub enum Operand {
     /// the meaning of this immediate is opcode-dependent, but is likely the absolute address for a
     /// `jmp` or `call` instruction.
     ImmU16 { imm: u16 },
+    /// the index of a single bit in a register, `0` being the least significant bit.
+    ///
+    /// the register and what happens to the bit depend on the opcode; see
+    /// [`Instruction::bit_access`].
+    BitIndex { bit: u8 },
+}
+
+/// the kind of an [`Operand`], without any of its contents.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OperandKind {
+    Nothing,
+    Register,
+    RegisterPair,
+    MemAbs16,
+    MemRegIndirect,
+    MemRegIndirectOffset,
+    BranchRelI8,
+    ImmU8,
+    ImmU16,
+    BitIndex,
+}
+
+impl Operand {
+    /// the kind of this operand.
+    pub fn kind(&self) -> OperandKind {
+        match self {
+            Operand::Nothing => OperandKind::Nothing,
+            Operand::Register { .. } => OperandKind::Register,
+            Operand::RegisterPair { .. } => OperandKind::RegisterPair,
+            Operand::MemAbs16 { .. } => OperandKind::MemAbs16,
+            Operand::MemRegIndirect { .. } => OperandKind::MemRegIndirect,
+            Operand::MemRegIndirectOffset { .. } => OperandKind::MemRegIndirectOffset,
+            Operand::BranchRelI8 { .. } => OperandKind::BranchRelI8,
+            Operand::ImmU8 { .. } => OperandKind::ImmU8,
+            Operand::ImmU16 { .. } => OperandKind::ImmU16,
+            Operand::BitIndex { .. } => OperandKind::BitIndex,
+        }
+    }
+
+    /// is this operand a register or register pair?
+    pub fn is_register(&self) -> bool {
+        matches!(self, Operand::Register { .. } | Operand::RegisterPair { .. })
+    }
+
+    /// is this operand a memory access, by any addressing mode?
+    pub fn is_memory(&self) -> bool {
+        matches!(self,
+            Operand::MemAbs16 { .. } |
+            Operand::MemRegIndirect { .. } |
+            Operand::MemRegIndirectOffset { .. }
+        )
+    }
+
+    /// is this operand an immediate? relative branch displacements are not considered immediates.
+    pub fn is_immediate(&self) -> bool {
+        matches!(self, Operand::ImmU8 { .. } | Operand::ImmU16 { .. })
+    }
+
+    /// the width of this operand's value, in bits.
+    ///
+    /// memory operands are 8 bits wide, as that is the size of the access rather than the address.
+    /// `Operand::BitIndex` is 1 bit wide, as that is the size of the value it selects.
+    /// `Operand::Nothing` has width 0.
+    pub fn width(&self) -> u8 {
+        match self {
+            Operand::Nothing => 0,
+            Operand::BitIndex { .. } => 1,
+            Operand::Register { .. } |
+            Operand::MemAbs16 { .. } |
+            Operand::MemRegIndirect { .. } |
+            Operand::MemRegIndirectOffset { .. } |
+            Operand::BranchRelI8 { .. } |
+            Operand::ImmU8 { .. } => 8,
+            Operand::RegisterPair { .. } |
+            Operand::ImmU16 { .. } => 16,
+        }
+    }
+}
+
+/// the condition a conditional branch tests.
+///
+/// conditional branches are encoded as `0x90..0x9f`, where the low three bits select a condition
+/// and bit 3 seems to select between branching when the condition holds or when it does not. only
+/// conditions `0` (zero flag) and `1` (carry flag) are understood.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum ConditionCode {
+    /// branch if the zero flag is set.
+    Z,
+    /// branch if the zero flag is clear.
+    NZ,
+    /// branch if the carry flag is set.
+    C,
+    /// branch if the carry flag is clear.
+    NC,
+    /// a yet-unknown condition. this is the low four bits of the branch opcode, so `0x2..0x7` are
+    /// the `jcc.lo` conditions and `0xa..0xf` are the `jcc.hi` conditions.
+    Unknown(u8),
+}
+
+impl ConditionCode {
+    /// the condition under which the opposite branch would be taken.
+    ///
+    /// for unknown conditions this assumes that `jcc.lo.N` and `jcc.hi.N` are opposites, as `jnz`
+    /// and `jz` are.
+    pub fn invert(&self) -> ConditionCode {
+        match self {
+            ConditionCode::Z => ConditionCode::NZ,
+            ConditionCode::NZ => ConditionCode::Z,
+            ConditionCode::C => ConditionCode::NC,
+            ConditionCode::NC => ConditionCode::C,
+            ConditionCode::Unknown(n) => ConditionCode::Unknown(n ^ 0b1000),
+        }
+    }
 }
 
 /// an avnera instruction's operation.
 ///
 /// instruction behavior is mostly unknown. the mnemonics here are best guesses from staring really
 /// hard at firmwares.
-#[derive(Copy, Clone, Hash, PartialEq, Eq)]
-enum Opcode {
+#[derive(Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Opcode {
     /// add with carry from register N into register 0
     Adc,
     /// mov from reigster N to register 0
@@ -241,6 +634,12 @@ enum Opcode {
     /// conditional branch (unknown condition, opcode in range `[98..9f]`). the first operand is
     /// the bit pattern selecting a yet-unknown condition.
     JccHi,
+    /// an opcode defined by a user-provided [`OpcodeExtension`].
+    ///
+    /// extensions are borrowed from the decoder that produced this instruction, so instructions
+    /// decoded by an extension cannot be serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Extension(&'static OpcodeExtension),
     /// return. where the stack is and what the stack pointer is are unknown, but it seems to do
     /// the thing.
     Ret,
@@ -266,6 +665,9 @@ enum Opcode {
     LoadRegPairC,
     /// store from register 0 into `[rM:rM+1 + C]`
     StoreRegPairC,
+    /// a byte that does not decode as any known instruction, produced only by a
+    /// [permissive](InstDecoder::permissive) decoder. the operand is the byte itself.
+    Undefined,
 }
 
 
@@ -274,8 +676,14 @@ enum Opcode {
 /// instruction decoding is best guess from staring really hard at firmwares. it's not clear if
 /// there are minor or substantial changes in the instruction set from part to part. this has been
 /// written purely from staring really hard at firmwares.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
-pub struct InstDecoder { }
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct InstDecoder {
+    part: Option<Part>,
+    overrides: &'static [OpcodeOverride],
+    extensions: &'static [OpcodeExtension],
+    permissive: bool,
+    strict: bool,
+}
 
 impl InstDecoder {
     /// decode a slice of bytes into an instruction (or error)
@@ -288,25 +696,164 @@ impl InstDecoder {
         InstDecoder::default()
             .decode(&mut U8Reader::new(data))
     }
-}
 
-impl Default for InstDecoder {
-    fn default() -> Self {
-        InstDecoder { }
+    /// a decoder for firmware written for `part`. no encodings specific to any part are known yet,
+    /// so this decodes the same as the default decoder; see [`Part`].
+    ///
+    /// ```
+    /// use yaxpeax_arch::{Decoder, U8Reader};
+    /// use yaxpeax_avnera::{InstDecoder, Part};
+    ///
+    /// let decoder = InstDecoder::for_part(Part::Av7301);
+    /// assert_eq!(decoder.part(), Some(Part::Av7301));
+    /// let inst = decoder.decode(&mut U8Reader::new(&[0xb9])).unwrap();
+    /// assert_eq!(inst.to_string(), "ret");
+    /// ```
+    pub fn for_part(part: Part) -> Self {
+        InstDecoder::default().with_part(Some(part))
+    }
+
+    /// set the part this decoder decodes for, or `None` for no particular part. see
+    /// [`InstDecoder::for_part`].
+    pub fn with_part(mut self, part: Option<Part>) -> Self {
+        self.part = part;
+        self
+    }
+
+    /// the part this decoder decodes for, if any.
+    pub fn part(&self) -> Option<Part> {
+        self.part
+    }
+
+    /// decode otherwise-invalid opcodes with the first matching definition in `extensions`.
+    ///
+    /// this is intended for trying out interpretations of bytes this crate does not know how to
+    /// decode, without forking the crate. see [`OpcodeExtension`] for an example.
+    ///
+    /// # panics
+    ///
+    /// panics if any extension describes instructions longer than the three-byte maximum of
+    /// `avnera` instructions; that is, if its operands take more than two bytes after the opcode.
+    pub fn with_extensions(mut self, extensions: &'static [OpcodeExtension]) -> Self {
+        for ext in extensions.iter() {
+            assert!(
+                ext.len() as u16 <= boundaries::MAX_INSTRUCTION_LEN,
+                "extension `{}` is {} bytes long, longer than any avnera instruction",
+                ext.mnemonic, ext.len()
+            );
+        }
+        self.extensions = extensions;
+        self
+    }
+
+    /// decode opcode bytes matching any of `overrides` with the first that matches, instead of as
+    /// this crate otherwise would.
+    ///
+    /// this is intended for trialing hypotheses about what opcodes do, including ones this crate
+    /// already decodes, without forking the crate. see [`OpcodeOverride`] for an example.
+    ///
+    /// # panics
+    ///
+    /// panics if an override describes instructions longer than three bytes, if its operands are
+    /// not the operands its opcode takes, such as `jmp` with a register, or if any byte it matches
+    /// decodes to operand values its opcode can not have, such as `jcc.lo` with condition `0`.
+    pub fn with_overrides(mut self, overrides: &'static [OpcodeOverride]) -> Self {
+        for ov in overrides.iter() {
+            assert!(
+                ov.len() as u16 <= boundaries::MAX_INSTRUCTION_LEN,
+                "override for opcode byte 0x{:02x} is {} bytes long, longer than any avnera instruction",
+                ov.pattern, ov.len()
+            );
+            assert!(
+                ov.conforms_to_opcode(),
+                "override for opcode byte 0x{:02x} does not decode to operands `{}` can take",
+                ov.pattern, ov.opcode
+            );
+        }
+        self.overrides = overrides;
+        self
+    }
+
+    /// a decoder that decodes invalid opcodes as one-byte `.byte` instructions rather than
+    /// returning an error.
+    ///
+    /// this is useful for linear sweeps over firmware images, where data and unknown instructions
+    /// are mixed in with code: decoding continues at the next byte without special handling.
+    /// running out of input partway through an instruction is still an error.
+    ///
+    /// ```
+    /// use yaxpeax_arch::{Decoder, U8Reader};
+    /// use yaxpeax_avnera::InstDecoder;
+    ///
+    /// let inst = InstDecoder::permissive().decode(&mut U8Reader::new(&[0xa0])).unwrap();
+    /// assert_eq!(inst.to_string(), ".byte 0xa0");
+    /// assert_eq!(inst.len(), 1);
+    /// ```
+    pub fn permissive() -> Self {
+        InstDecoder::default().with_permissive(true)
+    }
+
+    /// set whether this decoder decodes invalid opcodes as `.byte` instructions. see
+    /// [`InstDecoder::permissive`].
+    pub fn with_permissive(mut self, permissive: bool) -> Self {
+        self.permissive = permissive;
+        self
+    }
+
+    /// a decoder that rejects the speculative `op5xhi` and `op6xhi` encodings as invalid opcodes.
+    ///
+    /// these opcodes seem to exist, but what they do is entirely unknown. a region of bytes that
+    /// decodes as many of them is more likely data than code, so rejecting them helps tell the
+    /// two apart. rejected bytes are still offered to [extensions](InstDecoder::with_extensions)
+    /// and, if [permissive](InstDecoder::permissive), decoded as `.byte`.
+    pub fn strict() -> Self {
+        InstDecoder::default().with_strict(true)
+    }
+
+    /// set whether this decoder rejects speculative encodings. see [`InstDecoder::strict`].
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// decode `word`, which is not any known opcode, with extensions, or as an undefined byte.
+    fn decode_unknown<T: Reader<<Avnera as Arch>::Address, <Avnera as Arch>::Word>>(&self, word: u8, words: &mut T) -> Result<Instruction, <Avnera as Arch>::DecodeError> {
+        match extension::decode_extension(self.extensions, word, words) {
+            Err(e) if self.permissive && e.kind() == DecodeErrorKind::InvalidOpcode => {
+                Ok(Instruction::new_1op(Opcode::Undefined, Operand::ImmU8 { imm: word }))
+            }
+            res => res,
+        }
     }
 }
 
+fn next_u16<T: Reader<<Avnera as Arch>::Address, <Avnera as Arch>::Word>>(words: &mut T) -> Result<u16, <Avnera as Arch>::DecodeError> {
+    let i_lo = words.next()? as u16;
+    let i_hi = words.next()? as u16;
+    Ok(i_lo | (i_hi << 8))
+}
+
 impl Decoder<Avnera> for InstDecoder {
     fn decode_into<T: Reader<<Avnera as Arch>::Address, <Avnera as Arch>::Word>>(&self, inst: &mut Instruction, words: &mut T) -> Result<(), <Avnera as Arch>::DecodeError> {
+        let mut words = RecordingReader::new(words);
+        self.decode_recorded(inst, &mut words)
+            .map_err(|e| e.with_context(&words.bytes[..words.recorded]))
+    }
+}
+
+impl InstDecoder {
+    fn decode_recorded<T: Reader<<Avnera as Arch>::Address, <Avnera as Arch>::Word>>(&self, inst: &mut Instruction, words: &mut RecordingReader<T>) -> Result<(), <Avnera as Arch>::DecodeError> {
         inst.length = 0;
+        inst.bytes = [0; 3];
         inst.reset_operands();
         words.mark();
         let word = words.next()?;
 
-        fn next_u16<T: Reader<<Avnera as Arch>::Address, <Avnera as Arch>::Word>>(words: &mut T) -> Result<u16, <Avnera as Arch>::DecodeError> {
-            let i_lo = words.next()? as u16;
-            let i_hi = words.next()? as u16;
-            Ok(i_lo | (i_hi << 8))
+        if let Some(res) = extension::decode_override(self.overrides, word, words) {
+            *inst = res?;
+            inst.length = words.offset() as u8;
+            inst.bytes = words.bytes;
+            return Ok(());
         }
 
         use Opcode::*;
@@ -315,66 +862,70 @@ impl Decoder<Avnera> for InstDecoder {
 
         *inst = match word & 0xf8 {
             0x00 => {
-                Instruction::new_1op(Inc, Operand::Register { n: low_bits })
+                Instruction::new_1op(Inc, Operand::Register { reg: RegSpec { n: low_bits } })
             }
             0x08 => {
-                Instruction::new_1op(Adc, Operand::Register { n: low_bits })
+                Instruction::new_1op(Adc, Operand::Register { reg: RegSpec { n: low_bits } })
             }
             0x10 => {
-                Instruction::new_1op(MovRnR0, Operand::Register { n: low_bits })
+                Instruction::new_1op(MovRnR0, Operand::Register { reg: RegSpec { n: low_bits } })
             }
             0x18 => {
-                Instruction::new_1op(Or, Operand::Register { n: low_bits })
+                Instruction::new_1op(Or, Operand::Register { reg: RegSpec { n: low_bits } })
             }
             0x20 => {
-                Instruction::new_1op(And, Operand::Register { n: low_bits })
+                Instruction::new_1op(And, Operand::Register { reg: RegSpec { n: low_bits } })
             }
             0x28 => {
-                Instruction::new_1op(Xor, Operand::Register { n: low_bits })
+                Instruction::new_1op(Xor, Operand::Register { reg: RegSpec { n: low_bits } })
             }
             0x30 => {
-                Instruction::new_1op(Rcl, Operand::Register { n: low_bits })
+                Instruction::new_1op(Rcl, Operand::Register { reg: RegSpec { n: low_bits } })
             }
             0x38 => {
-                Instruction::new_1op(Rcr, Operand::Register { n: low_bits })
+                Instruction::new_1op(Rcr, Operand::Register { reg: RegSpec { n: low_bits } })
             }
             0x40 => {
-                Instruction::new_1op(Dec, Operand::Register { n: low_bits })
+                Instruction::new_1op(Dec, Operand::Register { reg: RegSpec { n: low_bits } })
             }
             0x48 => {
-                Instruction::new_1op(Sbc, Operand::Register { n: low_bits })
+                Instruction::new_1op(Sbc, Operand::Register { reg: RegSpec { n: low_bits } })
             }
             0x50 => {
-                Instruction::new_1op(Add, Operand::Register { n: low_bits })
+                Instruction::new_1op(Add, Operand::Register { reg: RegSpec { n: low_bits } })
             }
             0x58 => {
                 if word == 0x59 {
                     Instruction::new_0op(Scf)
+                } else if self.strict {
+                    self.decode_unknown(word, words)?
                 } else {
                     Instruction::new_1op(Op5xHi, Operand::ImmU8 { imm: low_bits })
                 }
             }
             0x60 => {
-                Instruction::new_1op(Bit, Operand::ImmU8 { imm: low_bits })
+                Instruction::new_1op(Bit, Operand::BitIndex { bit: low_bits })
             }
             0x68 => {
                 if word == 0x69 {
                     Instruction::new_0op(Ccf)
+                } else if self.strict {
+                    self.decode_unknown(word, words)?
                 } else {
                     Instruction::new_1op(Op6xHi, Operand::ImmU8 { imm: low_bits })
                 }
             }
             0x70 => {
-                Instruction::new_1op(MovR0Rn, Operand::Register { n: low_bits })
+                Instruction::new_1op(MovR0Rn, Operand::Register { reg: RegSpec { n: low_bits } })
             }
             0x78 => {
-                Instruction::new_1op(Cmp, Operand::Register { n: low_bits })
+                Instruction::new_1op(Cmp, Operand::Register { reg: RegSpec { n: low_bits } })
             }
             0x80 => {
-                Instruction::new_1op(Push, Operand::Register { n: low_bits })
+                Instruction::new_1op(Push, Operand::Register { reg: RegSpec { n: low_bits } })
             }
             0x88 => {
-                Instruction::new_1op(Pop, Operand::Register { n: low_bits })
+                Instruction::new_1op(Pop, Operand::Register { reg: RegSpec { n: low_bits } })
             },
             0x90 => {
                 let op = Operand::BranchRelI8 { rel: words.next()? as i8 };
@@ -408,67 +959,120 @@ impl Decoder<Avnera> for InstDecoder {
                         Operand::ImmU16 { imm: next_u16(words)? },
                     )
                 } else {
-                    return Err(StandardDecodeError::InvalidOpcode);
+                    self.decode_unknown(word, words)?
                 }
             },
             0xc0 => {
-                Instruction::new_1op(IncW, Operand::RegisterPair { n: low_bits })
+                Instruction::new_1op(IncW, Operand::RegisterPair { pair: RegPair { n: low_bits } })
             },
             0xc8 => {
                 Instruction::new_2op(StoreAbs16,
                     [
-                        Operand::Register { n: low_bits },
+                        Operand::Register { reg: RegSpec { n: low_bits } },
                         Operand::MemAbs16 { addr: next_u16(words)? },
                     ])
             },
             0xd0 => {
                 Instruction::new_1op(
                     StoreRegPair,
-                    Operand::MemRegIndirect { n: low_bits },
+                    Operand::MemRegIndirect { pair: RegPair { n: low_bits } },
                 )
             }
             0xd8 => {
                 Instruction::new_1op(
                     StoreRegPairC,
-                    Operand::MemRegIndirectOffset { n: low_bits, offs: words.next()? },
+                    Operand::MemRegIndirectOffset { pair: RegPair { n: low_bits }, offs: words.next()? },
                 )
             }
             0xe0 => {
                 Instruction::new_2op(LoadImm8,
                     [
-                        Operand::Register { n: low_bits },
+                        Operand::Register { reg: RegSpec { n: low_bits } },
                         Operand::ImmU8 { imm: words.next()? },
                     ])
             },
             0xe8 => {
                 Instruction::new_2op(LoadAbs16,
                     [
-                        Operand::Register { n: low_bits },
+                        Operand::Register { reg: RegSpec { n: low_bits } },
                         Operand::MemAbs16 { addr: next_u16(words)? },
                     ])
             },
             0xf0 => {
                 Instruction::new_1op(
                     LoadRegPair,
-                    Operand::MemRegIndirect { n: low_bits },
+                    Operand::MemRegIndirect { pair: RegPair { n: low_bits } },
                 )
             }
             0xf8 => {
                 Instruction::new_1op(
                     LoadRegPairC,
-                    Operand::MemRegIndirectOffset { n: low_bits, offs: words.next()? },
+                    Operand::MemRegIndirectOffset { pair: RegPair { n: low_bits }, offs: words.next()? },
                 )
             }
             _ => {
-                return Err(StandardDecodeError::InvalidOpcode);
+                self.decode_unknown(word, words)?
             }
         };
 
         inst.length = words.offset() as u8;
+        inst.bytes = words.bytes;
         Ok(())
     }
 }
 
+/// a `Reader` that keeps the first few bytes read through it, so decoded instructions can carry
+/// the bytes they were decoded from.
+///
+/// three bytes is enough for every instruction: [`InstDecoder::with_extensions`] and
+/// [`InstDecoder::with_overrides`] reject definitions of anything longer.
+struct RecordingReader<'r, T> {
+    inner: &'r mut T,
+    bytes: [u8; 3],
+    recorded: usize,
+}
+
+impl<'r, T> RecordingReader<'r, T> {
+    fn new(inner: &'r mut T) -> Self {
+        RecordingReader { inner, bytes: [0; 3], recorded: 0 }
+    }
+
+    fn record(&mut self, byte: u8) {
+        if let Some(slot) = self.bytes.get_mut(self.recorded) {
+            *slot = byte;
+            self.recorded += 1;
+        }
+    }
+}
+
+impl<'r, T: Reader<<Avnera as Arch>::Address, <Avnera as Arch>::Word>> Reader<<Avnera as Arch>::Address, <Avnera as Arch>::Word> for RecordingReader<'r, T> {
+    fn next(&mut self) -> Result<u8, yaxpeax_arch::ReadError> {
+        let byte = self.inner.next()?;
+        self.record(byte);
+        Ok(byte)
+    }
+
+    fn next_n(&mut self, buf: &mut [u8]) -> Result<(), yaxpeax_arch::ReadError> {
+        self.inner.next_n(buf)?;
+        for byte in buf.iter() {
+            self.record(*byte);
+        }
+        Ok(())
+    }
+
+    fn mark(&mut self) {
+        self.inner.mark()
+    }
+
+    fn offset(&mut self) -> <Avnera as Arch>::Address {
+        self.inner.offset()
+    }
+
+    fn total_offset(&mut self) -> <Avnera as Arch>::Address {
+        self.inner.total_offset()
+    }
+}
+
 impl Instruction {
     fn new_0op(opcode: Opcode) -> Self {
         Self {
@@ -476,6 +1080,7 @@ impl Instruction {
             operands: [Operand::Nothing, Operand::Nothing],
             operand_count: 0,
             length: 0,
+            bytes: [0; 3],
         }
     }
 
@@ -485,6 +1090,7 @@ impl Instruction {
             operands: [operand, Operand::Nothing],
             operand_count: 1,
             length: 0,
+            bytes: [0; 3],
         }
     }
 
@@ -494,6 +1100,7 @@ impl Instruction {
             operands,
             operand_count: 2,
             length: 0,
+            bytes: [0; 3],
         }
     }
 }
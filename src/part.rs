@@ -0,0 +1,15 @@
+/// a specific Avnera part, for decoding firmware written for that part.
+///
+/// these are the part names reported by devices seen so far. there is no documentation saying
+/// whether they share one instruction set, and nothing divergent has been spotted in the firmware
+/// looked at so far, so every part currently decodes the same way. hypotheses about encodings
+/// specific to a part can be tried out with
+/// [`InstDecoder::with_overrides`](crate::InstDecoder::with_overrides) until one is confirmed.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Part {
+    Av6201,
+    Av6301,
+    Av6302,
+    Av7201,
+    Av7301,
+}
@@ -0,0 +1,330 @@
+//! best-guess semantic metadata for `avnera` instructions.
+//!
+//! like the decoder itself, everything here comes from staring at firmware really hard. where an
+//! opcode's behavior is unknown, the tables here say as little as possible rather than guess.
+
+use crate::{Instruction, Opcode, Operand, RegSpec};
+
+/// a set of registers `r0..r7`, as read or written by an instruction.
+///
+/// register pairs are included as both of their registers. an `rN:rN+1` pair with `N` of 7 would
+/// refer to a nonexistent `r8`, which is not representable here and is omitted.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Default)]
+pub struct RegisterSet {
+    bits: u8,
+}
+
+impl RegisterSet {
+    /// a set with no registers in it.
+    pub const fn empty() -> Self {
+        RegisterSet { bits: 0 }
+    }
+
+    /// the set as a bitmask, where bit `N` is set if `rN` is in the set.
+    pub fn bits(&self) -> u8 {
+        self.bits
+    }
+
+    /// is `reg` in this set?
+    pub fn contains(&self, reg: RegSpec) -> bool {
+        self.bits & (1 << reg.num()) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn len(&self) -> u8 {
+        self.bits.count_ones() as u8
+    }
+
+    /// iterate over the registers in this set, lowest first.
+    pub fn iter(&self) -> impl Iterator<Item = RegSpec> {
+        let bits = self.bits;
+        (0..8u8).filter(move |n| bits & (1 << n) != 0).map(|n| RegSpec { n })
+    }
+
+    fn with(mut self, n: u8) -> Self {
+        if n < 8 {
+            self.bits |= 1 << n;
+        }
+        self
+    }
+
+    fn with_pair(self, n: u8) -> Self {
+        self.with(n).with(n.wrapping_add(1))
+    }
+}
+
+impl Instruction {
+    /// registers this instruction reads, including the implicit `r0` accumulator and both halves
+    /// of any register pair used as an address.
+    ///
+    /// this is `None` for the opcodes whose behavior is unknown (`op5xhi`, `op6xhi`, extensions,
+    /// and undefined bytes): they may read any register.
+    pub fn registers_read(&self) -> Option<RegisterSet> {
+        self.register_effects().map(|(read, _)| read)
+    }
+
+    /// registers this instruction writes, including the implicit `r0` accumulator.
+    ///
+    /// this is `None` for the opcodes whose behavior is unknown (`op5xhi`, `op6xhi`, extensions,
+    /// and undefined bytes): they may write any register.
+    pub fn registers_written(&self) -> Option<RegisterSet> {
+        self.register_effects().map(|(_, written)| written)
+    }
+
+    /// `(read, written)` register sets for this instruction, if they are known.
+    fn register_effects(&self) -> Option<(RegisterSet, RegisterSet)> {
+        use Opcode::*;
+
+        let none = RegisterSet::empty();
+        let r0 = RegisterSet::empty().with(0);
+
+        // the register number, or base of the register pair, of the first operand. every opcode
+        // with a register or register-pair operand has it in the first position.
+        let n = match self.operands[0] {
+            Operand::Register { reg } => reg.num(),
+            Operand::RegisterPair { pair } |
+            Operand::MemRegIndirect { pair } |
+            Operand::MemRegIndirectOffset { pair, .. } => pair.num(),
+            _ => 0,
+        };
+        let rn = RegisterSet::empty().with(n);
+
+        Some(match self.opcode {
+            Adc | Sbc | Add | Or | And | Xor => (r0.with(n), r0),
+            Cmp => (r0.with(n), none),
+            Inc | Dec | Rcl | Rcr => (rn, rn),
+            IncW => (none.with_pair(n), none.with_pair(n)),
+            MovRnR0 => (rn, r0),
+            MovR0Rn => (r0, rn),
+            Bit => (r0, r0),
+            Push => (rn, none),
+            Pop => (none, rn),
+            LoadImm8 | LoadAbs16 => (none, rn),
+            StoreAbs16 => (rn, none),
+            LoadRegPair | LoadRegPairC => (none.with_pair(n), r0),
+            StoreRegPair | StoreRegPairC => (r0.with_pair(n), none),
+            Scf | Ccf |
+            Jz | Jc | Jnz | Jnc | JccLo | JccHi |
+            Jmp | Call | Ret | Iret => (none, none),
+            Op5xHi | Op6xHi | Extension(_) | Undefined => { return None; }
+        })
+    }
+}
+
+/// whether a memory access reads or writes memory.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum AccessDirection {
+    Read,
+    Write,
+}
+
+/// a description of how an instruction accesses memory.
+///
+/// the addressing mode is whichever [`Operand`] is at index `operand` of the instruction:
+/// `MemAbs16`, `MemRegIndirect`, or `MemRegIndirectOffset`.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct MemAccess {
+    /// whether the instruction loads from or stores to memory.
+    pub direction: AccessDirection,
+    /// the index of the memory operand in the instruction.
+    pub operand: u8,
+    /// the address accessed, if it is known without any register state. this is only the case for
+    /// `MemAbs16` operands.
+    pub address: Option<u16>,
+}
+
+impl Instruction {
+    /// how this instruction accesses memory through an explicit memory operand, if it does.
+    ///
+    /// `push`, `pop`, `call`, `ret`, and `iret` presumably access a stack in memory, but where the
+    /// stack is and how the stack pointer is managed are unknown, so they are not reported here.
+    pub fn memory_access(&self) -> Option<MemAccess> {
+        use Opcode::*;
+
+        let direction = match self.opcode {
+            LoadAbs16 | LoadRegPair | LoadRegPairC => AccessDirection::Read,
+            StoreAbs16 | StoreRegPair | StoreRegPairC => AccessDirection::Write,
+            _ => { return None; }
+        };
+
+        self.operands().enumerate().find_map(|(idx, op)| {
+            let address = match op {
+                Operand::MemAbs16 { addr } => Some(addr),
+                Operand::MemRegIndirect { .. } |
+                Operand::MemRegIndirectOffset { .. } => None,
+                _ => { return None; }
+            };
+            Some(MemAccess {
+                direction,
+                operand: idx as u8,
+                address,
+            })
+        })
+    }
+}
+
+/// what a bit operation does to the bit it selects.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum BitOperation {
+    /// the bit is read, and the register is left unchanged.
+    Test,
+    /// the bit is set to 1.
+    Set,
+    /// the bit is cleared to 0.
+    Clear,
+    /// the bit is inverted.
+    Toggle,
+}
+
+/// a description of how an instruction operates on a single bit of a register.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct BitAccess {
+    /// the register holding the bit.
+    pub register: RegSpec,
+    /// the index of the bit, `0` being the least significant bit.
+    pub bit: u8,
+    /// what is done to the bit.
+    pub operation: BitOperation,
+    /// the index of the `Operand::BitIndex` in the instruction.
+    pub operand: u8,
+}
+
+impl Instruction {
+    /// how this instruction operates on a single bit, if it does.
+    ///
+    /// `bit` is the only opcode known to do this. it seems to toggle a bit of `r0`, but this is
+    /// [`Confidence::Tentative`]; it may turn out to be a test instead.
+    pub fn bit_access(&self) -> Option<BitAccess> {
+        let operation = match self.opcode {
+            Opcode::Bit => BitOperation::Toggle,
+            _ => { return None; }
+        };
+
+        self.operands().enumerate().find_map(|(idx, op)| {
+            if let Operand::BitIndex { bit } = op {
+                Some(BitAccess {
+                    register: RegSpec::r0(),
+                    bit,
+                    operation,
+                    operand: idx as u8,
+                })
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// how an instruction uses a single status flag.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum FlagEffect {
+    /// the flag is neither read nor written.
+    Unaffected,
+    /// the flag is read but not written.
+    Read,
+    /// the flag is written without being read.
+    Written,
+    /// the flag is read, then written.
+    ReadWritten,
+    /// the instruction's effect on this flag is not known.
+    Unknown,
+}
+
+impl FlagEffect {
+    /// might this flag be read? `Unknown` effects might be.
+    pub fn may_read(&self) -> bool {
+        matches!(self, FlagEffect::Read | FlagEffect::ReadWritten | FlagEffect::Unknown)
+    }
+
+    /// might this flag be written? `Unknown` effects might be.
+    pub fn may_write(&self) -> bool {
+        matches!(self, FlagEffect::Written | FlagEffect::ReadWritten | FlagEffect::Unknown)
+    }
+}
+
+/// an instruction's effects on the carry and zero flags.
+///
+/// carry and zero are the only flags with any evidence of existing: `scf`/`ccf`, `adc`/`sbc`, and
+/// the `jc`/`jnc`/`jz`/`jnz` branches. the remaining conditions of `jcc.lo`/`jcc.hi` presumably
+/// test other flags, which are not modeled yet.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct FlagEffects {
+    pub carry: FlagEffect,
+    pub zero: FlagEffect,
+}
+
+impl Instruction {
+    /// this instruction's best-guess effects on the carry and zero flags.
+    ///
+    /// where there is no evidence either way, such as whether loads set the zero flag, the effect
+    /// is [`FlagEffect::Unknown`].
+    pub fn flag_effects(&self) -> FlagEffects {
+        use Opcode::*;
+        use FlagEffect::*;
+
+        let (carry, zero) = match self.opcode {
+            Adc | Sbc => (ReadWritten, Written),
+            Add | Cmp => (Written, Written),
+            Or | And | Xor | Inc | Dec => (Unknown, Written),
+            Rcl | Rcr => (ReadWritten, Unknown),
+            Scf | Ccf => (Written, Unaffected),
+            Jz | Jnz => (Unaffected, Read),
+            Jc | Jnc => (Read, Unaffected),
+            MovRnR0 | MovR0Rn | Pop |
+            LoadImm8 | LoadAbs16 | LoadRegPair | LoadRegPairC => (Unaffected, Unknown),
+            Push | StoreAbs16 | StoreRegPair | StoreRegPairC |
+            Jmp | Call | Ret => (Unaffected, Unaffected),
+            IncW | Bit | Iret |
+            JccLo | JccHi |
+            Op5xHi | Op6xHi | Extension(_) | Undefined => (Unknown, Unknown),
+        };
+
+        FlagEffects { carry, zero }
+    }
+}
+
+/// how well an opcode's behavior is understood.
+///
+/// none of this instruction set is documented, so every opcode is a guess to some degree. this
+/// ranks how much evidence there is for each guess.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// nothing is known about what this opcode does.
+    Unknown,
+    /// the general behavior is plausible, but important details are not known; what condition a
+    /// `jcc.lo` branch tests, or what exactly `bit` does to its bit.
+    Tentative,
+    /// the behavior is consistent with everything seen in firmware so far.
+    Confident,
+}
+
+impl Opcode {
+    /// how well this opcode's behavior is understood.
+    pub fn confidence(&self) -> Confidence {
+        use Opcode::*;
+
+        match self {
+            Adc | MovRnR0 | Or | And | Xor | Inc | Dec | IncW | Sbc | Add |
+            Scf | Ccf | MovR0Rn | Cmp | Push | Pop |
+            Jnz | Jnc | Jz | Jc | Ret | Jmp | Call |
+            LoadImm8 | LoadAbs16 | StoreAbs16 |
+            LoadRegPair | StoreRegPair | LoadRegPairC | StoreRegPairC => Confidence::Confident,
+            Rcl | Rcr | Bit | Iret | JccLo | JccHi => Confidence::Tentative,
+            Op5xHi | Op6xHi | Extension(_) | Undefined => Confidence::Unknown,
+        }
+    }
+}
+
+impl Instruction {
+    /// is anything known about what this instruction does?
+    ///
+    /// this is false for the speculative `op5xhi` and `op6xhi` encodings, instructions decoded by
+    /// an [`crate::OpcodeExtension`], and undefined bytes from a permissive decoder. analyses can
+    /// use this to flag regions where their results rest on unknown behavior.
+    pub fn semantics_known(&self) -> bool {
+        self.opcode.confidence() != Confidence::Unknown
+    }
+}
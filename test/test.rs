@@ -30,3 +30,1197 @@ fn test_disassembly() {
     test_display(&[0x84], "push r4");
     test_display(&[0xc4], "incw r4:r5");
 }
+
+#[test]
+fn test_instruction_boundaries() {
+    use yaxpeax_avnera::{InstDecoder, InstructionBoundaries};
+
+    // `jmp 0xd98a`, `ret`, an undecodable byte, `r4 <- 0x0e`
+    let data = [0xbc, 0x8a, 0xd9, 0xb9, 0xa0, 0xe4, 0x0e];
+    let index = InstructionBoundaries::from_linear_sweep(&InstDecoder::default(), &data, 0x1000);
+
+    assert!(index.is_instruction_start(0x1000));
+    assert!(!index.is_instruction_start(0x1001));
+    assert!(!index.is_instruction_start(0x1002));
+    assert!(index.is_instruction_start(0x1003));
+    assert!(!index.is_instruction_start(0x1004));
+    assert!(index.is_instruction_start(0x1005));
+    assert!(!index.is_instruction_start(0x1006));
+
+    assert_eq!(index.prev_instruction(0x1003), Some(0x1000));
+    assert_eq!(index.prev_instruction(0x1005), Some(0x1003));
+    assert_eq!(index.prev_instruction(0x1000), None);
+
+    // data running past the end of the address space is ignored rather than wrapping around.
+    let index = InstructionBoundaries::from_linear_sweep(&InstDecoder::default(), &[0xb9, 0xb9, 0xb9], 0xfffe);
+    assert!(index.is_instruction_start(0xfffe));
+    assert!(index.is_instruction_start(0xffff));
+    assert!(!index.is_instruction_start(0x0000));
+
+    // the index agrees with the decoder it was built with.
+    let index = InstructionBoundaries::from_linear_sweep(&InstDecoder::permissive(), &data, 0x1000);
+    assert!(index.is_instruction_start(0x1004));
+    assert_eq!(index.prev_instruction(0x1005), Some(0x1004));
+}
+
+#[test]
+fn test_prev_instruction_at() {
+    use yaxpeax_avnera::InstDecoder;
+
+    let decoder = InstDecoder::default();
+
+    // `ret`, `r4 <- 0x0e`, `ret`. `0x0e` alone is `adc r0, r6`, but the `0xe4` before it only
+    // decodes as the start of a load.
+    let data = [0xb9, 0xe4, 0x0e, 0xb9];
+    let (addr, inst) = decoder.prev_instruction_at(&data, 0x2000, 0x2003).unwrap();
+    assert_eq!(addr, 0x2001);
+    assert_eq!(inst.to_string(), "r4 <- 0x0e");
+
+    let (addr, inst) = decoder.prev_instruction_at(&data, 0x2000, 0x2001).unwrap();
+    assert_eq!(addr, 0x2000);
+    assert_eq!(inst.to_string(), "ret");
+
+    assert!(decoder.prev_instruction_at(&data, 0x2000, 0x2000).is_none());
+    assert!(decoder.prev_instruction_at(&data, 0x2000, 0x1fff).is_none());
+    assert!(decoder.prev_instruction_at(&data, 0x2000, 0x2005).is_none());
+
+    // an undecodable byte is not a previous instruction.
+    assert!(decoder.prev_instruction_at(&[0xa0], 0, 1).is_none());
+}
+
+#[test]
+fn test_operands_iter() {
+    use yaxpeax_avnera::{InstDecoder, Operand, RegSpec};
+
+    let inst = InstDecoder::decode_slice(&[0xb9]).unwrap();
+    assert_eq!(inst.operands().count(), 0);
+
+    let inst = InstDecoder::decode_slice(&[0x84]).unwrap();
+    let operands: Vec<Operand> = inst.operands().collect();
+    assert_eq!(operands, [Operand::Register { reg: RegSpec::r4() }]);
+
+    let inst = InstDecoder::decode_slice(&[0x92, 0xfe]).unwrap();
+    let operands: Vec<Operand> = inst.operands().collect();
+    assert_eq!(operands, [Operand::ImmU8 { imm: 2 }, Operand::BranchRelI8 { rel: -2 }]);
+}
+
+#[test]
+fn test_control_flow_classification() {
+    use yaxpeax_arch::U8Reader;
+    use yaxpeax_avnera::{InstDecoder, OpcodeExtension, OperandSpec};
+
+    static DJNZ: [OpcodeExtension; 1] = [
+        OpcodeExtension {
+            pattern: 0xa8,
+            mask: 0xf8,
+            mnemonic: "djnz",
+            operands: [OperandSpec::RegisterLow3, OperandSpec::BranchRelI8],
+            description: "decrement rN, branch if not zero?",
+        },
+    ];
+
+    // (bytes, is_branch, is_conditional, is_call, is_return)
+    let cases: &[(&[u8], bool, bool, bool, bool)] = &[
+        (&[0xbc, 0x8a, 0xd9], true, false, false, false),
+        (&[0x90, 0x50], true, true, false, false),
+        (&[0x99, 0x50], true, true, false, false),
+        (&[0x9a, 0x50], true, true, false, false),
+        (&[0xbf, 0x8a, 0xd9], false, false, true, false),
+        (&[0xb9], false, false, false, true),
+        (&[0xba], false, false, false, true),
+        (&[0x29], false, false, false, false),
+        (&[0xe4, 0x0e], false, false, false, false),
+    ];
+
+    for (bytes, branch, conditional, call, ret) in cases.iter() {
+        let inst = InstDecoder::decode_slice(bytes).unwrap();
+        assert_eq!(inst.is_branch(), *branch, "is_branch for {}", inst);
+        assert_eq!(inst.is_conditional(), *conditional, "is_conditional for {}", inst);
+        assert_eq!(inst.is_call(), *call, "is_call for {}", inst);
+        assert_eq!(inst.is_return(), *ret, "is_return for {}", inst);
+    }
+
+    // an extension with a relative branch operand is a branch, so it agrees with `branch_target`.
+    let decoder = InstDecoder::default().with_extensions(&DJNZ);
+    let inst = decoder.decode(&mut U8Reader::new(&[0xab, 0xfe])).unwrap();
+    assert!(inst.is_branch());
+    assert!(inst.is_conditional());
+    assert!(!inst.is_call());
+    assert_eq!(inst.branch_target(0x1000), Some(0x1000));
+}
+
+#[test]
+fn test_branch_target() {
+    use yaxpeax_avnera::InstDecoder;
+
+    let jnz_fwd = InstDecoder::decode_slice(&[0x90, 0x50]).unwrap();
+    assert_eq!(jnz_fwd.branch_target(0x1000), Some(0x1052));
+
+    let jcc_back = InstDecoder::decode_slice(&[0x9a, 0xfe]).unwrap();
+    assert_eq!(jcc_back.branch_target(0x1000), Some(0x1000));
+
+    // relative branches wrap around both ends of the address space.
+    assert_eq!(jnz_fwd.branch_target(0xfff0), Some(0x0042));
+    let jz_back = InstDecoder::decode_slice(&[0x98, 0x80]).unwrap();
+    assert_eq!(jz_back.branch_target(0x0010), Some(0xff92));
+
+    let jmp = InstDecoder::decode_slice(&[0xbc, 0x8a, 0xd9]).unwrap();
+    assert_eq!(jmp.branch_target(0x1000), Some(0xd98a));
+    let call = InstDecoder::decode_slice(&[0xbf, 0x8a, 0xd9]).unwrap();
+    assert_eq!(call.branch_target(0x1000), Some(0xd98a));
+
+    assert_eq!(InstDecoder::decode_slice(&[0xb9]).unwrap().branch_target(0x1000), None);
+    assert_eq!(InstDecoder::decode_slice(&[0x29]).unwrap().branch_target(0x1000), None);
+}
+
+#[test]
+fn test_register_sets() {
+    use yaxpeax_avnera::{InstDecoder, RegSpec};
+
+    fn regs(bytes: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let inst = InstDecoder::decode_slice(bytes).unwrap();
+        (
+            inst.registers_read().unwrap().iter().map(|reg| reg.num()).collect(),
+            inst.registers_written().unwrap().iter().map(|reg| reg.num()).collect(),
+        )
+    }
+
+    // r0 ^= r1
+    assert_eq!(regs(&[0x29]), (vec![0, 1], vec![0]));
+    // r0 <- r3
+    assert_eq!(regs(&[0x13]), (vec![3], vec![0]));
+    // r3 <- r0
+    assert_eq!(regs(&[0x73]), (vec![0], vec![3]));
+    // cmp r0, r2
+    assert_eq!(regs(&[0x7a]), (vec![0, 2], vec![]));
+    // incw r4:r5
+    assert_eq!(regs(&[0xc4]), (vec![4, 5], vec![4, 5]));
+    // r4 <- 0x0e
+    assert_eq!(regs(&[0xe4, 0x0e]), (vec![], vec![4]));
+    // [0xedf2] <- r1
+    assert_eq!(regs(&[0xc9, 0xf2, 0xed]), (vec![1], vec![]));
+    // r0 <- [r6:r7 + 0x40]
+    assert_eq!(regs(&[0xfe, 0x40]), (vec![6, 7], vec![0]));
+    // [r4:r5] <- r0
+    assert_eq!(regs(&[0xd4]), (vec![0, 4, 5], vec![]));
+    // push r4, pop r4
+    assert_eq!(regs(&[0x84]), (vec![4], vec![]));
+    assert_eq!(regs(&[0x8c]), (vec![], vec![4]));
+    // jmp 0xd98a
+    assert_eq!(regs(&[0xbc, 0x8a, 0xd9]), (vec![], vec![]));
+
+    // a pair based at r7 has no r8 to include.
+    let inst = InstDecoder::decode_slice(&[0xc7]).unwrap();
+    assert_eq!(inst.registers_read().unwrap().bits(), 0b1000_0000);
+    assert!(inst.registers_read().unwrap().contains(RegSpec::r7()));
+    assert!(!inst.registers_read().unwrap().contains(RegSpec::r0()));
+
+    // nothing is known about what registers the unknown opcodes use.
+    for bytes in [[0x5a], [0x6b]].iter() {
+        let inst = InstDecoder::decode_slice(bytes).unwrap();
+        assert_eq!(inst.registers_read(), None);
+        assert_eq!(inst.registers_written(), None);
+    }
+}
+
+#[test]
+fn test_memory_access() {
+    use yaxpeax_avnera::{AccessDirection, InstDecoder, MemAccess, Operand, RegPair};
+
+    let inst = InstDecoder::decode_slice(&[0xe9, 0xf2, 0xed]).unwrap();
+    assert_eq!(inst.memory_access(), Some(MemAccess {
+        direction: AccessDirection::Read,
+        operand: 1,
+        address: Some(0xedf2),
+    }));
+
+    let inst = InstDecoder::decode_slice(&[0xc9, 0xf2, 0xed]).unwrap();
+    assert_eq!(inst.memory_access(), Some(MemAccess {
+        direction: AccessDirection::Write,
+        operand: 1,
+        address: Some(0xedf2),
+    }));
+
+    let inst = InstDecoder::decode_slice(&[0xdc, 0x40]).unwrap();
+    let access = inst.memory_access().unwrap();
+    assert_eq!(access.direction, AccessDirection::Write);
+    assert_eq!(access.address, None);
+    assert_eq!(inst.operand(access.operand), Some(Operand::MemRegIndirectOffset { pair: RegPair::r4_r5(), offs: 0x40 }));
+
+    let inst = InstDecoder::decode_slice(&[0xf6]).unwrap();
+    assert_eq!(inst.memory_access().map(|a| a.direction), Some(AccessDirection::Read));
+
+    assert_eq!(InstDecoder::decode_slice(&[0xe4, 0x0e]).unwrap().memory_access(), None);
+    assert_eq!(InstDecoder::decode_slice(&[0x84]).unwrap().memory_access(), None);
+}
+
+#[test]
+fn test_opcode_extensions() {
+    use yaxpeax_arch::U8Reader;
+    use yaxpeax_avnera::{InstDecoder, OpcodeExtension, OperandSpec};
+
+    static EXTENSIONS: [OpcodeExtension; 2] = [
+        OpcodeExtension {
+            pattern: 0xa8,
+            mask: 0xf8,
+            mnemonic: "ldx",
+            operands: [OperandSpec::RegisterLow3, OperandSpec::MemAbs16],
+            description: "load with some unknown side effect",
+        },
+        OpcodeExtension {
+            pattern: 0xbb,
+            mask: 0xff,
+            mnemonic: "halt",
+            operands: [OperandSpec::Nothing, OperandSpec::ImmU8],
+            description: "operands after `Nothing` are ignored",
+        },
+    ];
+
+    let decoder = InstDecoder::default().with_extensions(&EXTENSIONS);
+
+    let inst = decoder.decode(&mut U8Reader::new(&[0xaa, 0x34, 0x12])).unwrap();
+    assert_eq!(inst.to_string(), "ldx r2, [0x1234]");
+    assert_eq!(inst.len(), 3);
+    assert_eq!(inst.operand_count(), 2);
+    assert_eq!(inst.extension().map(|ext| ext.mnemonic), Some("ldx"));
+
+    let inst = decoder.decode(&mut U8Reader::new(&[0xbb, 0x00])).unwrap();
+    assert_eq!(inst.to_string(), "halt");
+    assert_eq!(inst.len(), 1);
+
+    // extensions never replace an opcode the decoder already knows.
+    let inst = decoder.decode(&mut U8Reader::new(&[0xb9])).unwrap();
+    assert_eq!(inst.to_string(), "ret");
+    assert!(inst.extension().is_none());
+
+    // bytes no extension matches are still invalid.
+    assert!(decoder.decode(&mut U8Reader::new(&[0xa0])).is_err());
+    // and extensions still need all their operand bytes.
+    assert!(decoder.decode(&mut U8Reader::new(&[0xaa, 0x34])).is_err());
+}
+
+#[test]
+fn test_flag_effects() {
+    use yaxpeax_avnera::{FlagEffect, InstDecoder};
+
+    fn flags(bytes: &[u8]) -> (FlagEffect, FlagEffect) {
+        let effects = InstDecoder::decode_slice(bytes).unwrap().flag_effects();
+        (effects.carry, effects.zero)
+    }
+
+    // adc r0, r2
+    assert_eq!(flags(&[0x0a]), (FlagEffect::ReadWritten, FlagEffect::Written));
+    // scf, ccf
+    assert_eq!(flags(&[0x59]), (FlagEffect::Written, FlagEffect::Unaffected));
+    assert_eq!(flags(&[0x69]), (FlagEffect::Written, FlagEffect::Unaffected));
+    // cmp r0, r1
+    assert_eq!(flags(&[0x79]), (FlagEffect::Written, FlagEffect::Written));
+    // jnz, jc
+    assert_eq!(flags(&[0x90, 0x50]), (FlagEffect::Unaffected, FlagEffect::Read));
+    assert_eq!(flags(&[0x99, 0x50]), (FlagEffect::Read, FlagEffect::Unaffected));
+    // [0xedf2] <- r1
+    assert_eq!(flags(&[0xc9, 0xf2, 0xed]), (FlagEffect::Unaffected, FlagEffect::Unaffected));
+    // op5xhi, jcc.lo
+    assert_eq!(flags(&[0x5a]), (FlagEffect::Unknown, FlagEffect::Unknown));
+    assert_eq!(flags(&[0x92, 0x50]), (FlagEffect::Unknown, FlagEffect::Unknown));
+
+    assert!(FlagEffect::Unknown.may_read() && FlagEffect::Unknown.may_write());
+    assert!(!FlagEffect::Unaffected.may_read() && !FlagEffect::Unaffected.may_write());
+    assert!(FlagEffect::Read.may_read() && !FlagEffect::Read.may_write());
+}
+
+#[test]
+fn test_condition_codes() {
+    use yaxpeax_avnera::{ConditionCode, InstDecoder};
+
+    fn cond(byte: u8) -> Option<ConditionCode> {
+        InstDecoder::decode_slice(&[byte, 0x10]).unwrap().condition()
+    }
+
+    assert_eq!(cond(0x90), Some(ConditionCode::NZ));
+    assert_eq!(cond(0x91), Some(ConditionCode::NC));
+    assert_eq!(cond(0x92), Some(ConditionCode::Unknown(0x2)));
+    assert_eq!(cond(0x98), Some(ConditionCode::Z));
+    assert_eq!(cond(0x99), Some(ConditionCode::C));
+    assert_eq!(cond(0x9f), Some(ConditionCode::Unknown(0xf)));
+
+    assert_eq!(InstDecoder::decode_slice(&[0xbc, 0x8a, 0xd9]).unwrap().condition(), None);
+
+    // inverting a condition selects the branch with bit 3 of the opcode flipped.
+    for byte in 0x90..=0x9fu8 {
+        assert_eq!(cond(byte).unwrap().invert(), cond(byte ^ 0b1000).unwrap());
+    }
+}
+
+#[test]
+fn test_regspec() {
+    use yaxpeax_avnera::{RegPair, RegSpec};
+
+    assert_eq!(RegSpec::from_num(3), Some(RegSpec::r3()));
+    assert_eq!(RegSpec::from_num(8), None);
+    assert_eq!(RegSpec::r7().num(), 7);
+    assert_eq!(RegSpec::r5().to_string(), "r5");
+
+    assert_eq!(RegPair::from_base(4), Some(RegPair::r4_r5()));
+    assert_eq!(RegPair::from_base(8), None);
+    assert_eq!(RegPair::r6_r7().to_string(), "r6:r7");
+    assert_eq!(RegPair::r2_r3().first(), RegSpec::r2());
+    assert_eq!(RegPair::r2_r3().second(), Some(RegSpec::r3()));
+    assert_eq!(RegPair::from_base(7).unwrap().second(), None);
+}
+
+#[test]
+fn test_operand_kinds() {
+    use yaxpeax_avnera::{InstDecoder, OperandKind};
+
+    // (bytes, operand index, kind, register, memory, immediate, width)
+    type Case = (&'static [u8], u8, OperandKind, bool, bool, bool, u8);
+    let cases: &[Case] = &[
+        (&[0x84], 0, OperandKind::Register, true, false, false, 8),
+        (&[0xc4], 0, OperandKind::RegisterPair, true, false, false, 16),
+        (&[0xc9, 0xf2, 0xed], 1, OperandKind::MemAbs16, false, true, false, 8),
+        (&[0xd4], 0, OperandKind::MemRegIndirect, false, true, false, 8),
+        (&[0xfe, 0x40], 0, OperandKind::MemRegIndirectOffset, false, true, false, 8),
+        (&[0x90, 0x50], 0, OperandKind::BranchRelI8, false, false, false, 8),
+        (&[0xe4, 0x0e], 1, OperandKind::ImmU8, false, false, true, 8),
+        (&[0xbc, 0x8a, 0xd9], 0, OperandKind::ImmU16, false, false, true, 16),
+    ];
+
+    for (bytes, idx, kind, register, memory, immediate, width) in cases.iter() {
+        let op = InstDecoder::decode_slice(bytes).unwrap().operand(*idx).unwrap();
+        assert_eq!(op.kind(), *kind);
+        assert_eq!(op.is_register(), *register, "is_register for {}", op);
+        assert_eq!(op.is_memory(), *memory, "is_memory for {}", op);
+        assert_eq!(op.is_immediate(), *immediate, "is_immediate for {}", op);
+        assert_eq!(op.width(), *width, "width for {}", op);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_roundtrip() {
+    use yaxpeax_arch::U8Reader;
+    use yaxpeax_avnera::{InstDecoder, Instruction, OpcodeExtension, OperandSpec};
+
+    for bytes in [&[0xc9, 0xf2, 0xed][..], &[0xfe, 0x40], &[0x92, 0xfe], &[0xb9], &[0xc4]].iter() {
+        let inst = InstDecoder::decode_slice(bytes).unwrap();
+        let json = serde_json::to_string(&inst).unwrap();
+        let roundtripped: Instruction = serde_json::from_str(&json).unwrap();
+        assert_eq!(inst, roundtripped);
+    }
+
+    // register numbers are validated on the way back in.
+    let json = serde_json::to_string(&InstDecoder::decode_slice(&[0x84]).unwrap()).unwrap();
+    assert!(json.contains("\"reg\":4"));
+    let bad = json.replace("\"reg\":4", "\"reg\":9");
+    assert!(serde_json::from_str::<Instruction>(&bad).is_err());
+
+    // so is agreement between the opcode and operands, and the length and bytes are recomputed.
+    let json = serde_json::to_string(&InstDecoder::decode_slice(&[0xbf, 0x8a, 0xd9]).unwrap()).unwrap();
+    assert!(json.contains("\"operand_count\":1"));
+    assert!(serde_json::from_str::<Instruction>(&json.replace("\"operand_count\":1", "\"operand_count\":5")).is_err());
+    assert!(serde_json::from_str::<Instruction>(&json.replace("\"operand_count\":1", "\"operand_count\":0")).is_err());
+    assert!(serde_json::from_str::<Instruction>(&json.replace("\"Call\"", "\"JccLo\"")).is_err());
+    assert!(serde_json::from_str::<Instruction>(&json.replace("\"Call\"", "\"Ret\"")).is_err());
+    assert!(json.contains("\"length\":3"));
+    let inst: Instruction = serde_json::from_str(&json.replace("\"length\":3", "\"length\":9")).unwrap();
+    assert_eq!(inst.len(), 3);
+    assert_eq!(inst.bytes(), &[0xbf, 0x8a, 0xd9]);
+
+    // instructions decoded by an extension borrow it, and can't be serialized.
+    static EXTENSIONS: [OpcodeExtension; 1] = [
+        OpcodeExtension {
+            pattern: 0xa0,
+            mask: 0xff,
+            mnemonic: "nop?",
+            operands: [OperandSpec::Nothing, OperandSpec::Nothing],
+            description: "",
+        },
+    ];
+    let decoder = InstDecoder::default().with_extensions(&EXTENSIONS);
+    let inst = decoder.decode(&mut U8Reader::new(&[0xa0])).unwrap();
+    assert!(serde_json::to_string(&inst).is_err());
+}
+
+#[test]
+fn test_build() {
+    use yaxpeax_arch::{Decoder, U8Reader};
+    use yaxpeax_avnera::{BuildError, InstDecoder, Instruction, Opcode, OpcodeExtension, Operand, OperandKind, OperandSpec, RegPair, RegSpec};
+
+    // every instruction any decoder produces can be rebuilt from its opcode and operands.
+    let decoders = [InstDecoder::default(), InstDecoder::permissive(), InstDecoder::strict().with_permissive(true)];
+    for decoder in decoders.iter() {
+        for byte in 0..=0xffu8 {
+            let inst = match decoder.decode(&mut U8Reader::new(&[byte, 0x34, 0x12])) {
+                Ok(inst) => inst,
+                Err(_) => continue,
+            };
+            let operands: Vec<Operand> = inst.operands().collect();
+            assert_eq!(Instruction::build(inst.opcode(), &operands), Ok(inst), "rebuilding {}", inst);
+        }
+    }
+
+    let inst = Instruction::build(Opcode::StoreRegPairC, &[
+        Operand::MemRegIndirectOffset { pair: RegPair::r4_r5(), offs: 0x40 },
+    ]).unwrap();
+    assert_eq!(inst.to_string(), "[r4:r5 + 0x40] <- r0");
+    assert_eq!(inst.len(), 2);
+
+    assert_eq!(
+        Instruction::build(Opcode::Ret, &[Operand::Register { reg: RegSpec::r0() }]),
+        Err(BuildError::OperandCount { expected: 0 })
+    );
+    assert_eq!(
+        Instruction::build(Opcode::Jmp, &[Operand::ImmU8 { imm: 0x10 }]),
+        Err(BuildError::OperandKind { index: 0, expected: OperandKind::ImmU16 })
+    );
+    // `jcc.lo.1` is `jnc`, and `op5xhi 0x01` is `scf`.
+    assert_eq!(
+        Instruction::build(Opcode::JccLo, &[Operand::ImmU8 { imm: 1 }, Operand::BranchRelI8 { rel: 0 }]),
+        Err(BuildError::OperandValue { index: 0 })
+    );
+    assert_eq!(
+        Instruction::build(Opcode::Op5xHi, &[Operand::ImmU8 { imm: 1 }]),
+        Err(BuildError::OperandValue { index: 0 })
+    );
+    assert_eq!(
+        Instruction::build(Opcode::Bit, &[Operand::BitIndex { bit: 8 }]),
+        Err(BuildError::OperandValue { index: 0 })
+    );
+
+    static LONG: OpcodeExtension = OpcodeExtension {
+        pattern: 0xa1,
+        mask: 0xff,
+        mnemonic: "long",
+        operands: [OperandSpec::ImmU16, OperandSpec::MemAbs16],
+        description: "five bytes is more than any instruction seen",
+    };
+    assert_eq!(
+        Instruction::build(Opcode::Extension(&LONG), &[Operand::ImmU16 { imm: 1 }, Operand::MemAbs16 { addr: 2 }]),
+        Err(BuildError::TooLong { length: 5 })
+    );
+}
+
+#[test]
+fn test_encode() {
+    use yaxpeax_arch::U8Reader;
+    use yaxpeax_avnera::{EncodeError, InstDecoder, OpcodeExtension, OperandSpec};
+
+    // every decodable instruction encodes back to the bytes it was decoded from.
+    for byte in 0..=0xffu8 {
+        let bytes = [byte, 0x34, 0x12];
+        let inst = match InstDecoder::decode_slice(&bytes) {
+            Ok(inst) => inst,
+            Err(_) => continue,
+        };
+        let mut buf = [0u8; 3];
+        let len = inst.encode_to(&mut buf).unwrap();
+        assert_eq!(len, inst.len() as usize);
+        assert_eq!(&buf[..len], &bytes[..len], "encoding {}", inst);
+    }
+
+    let inst = InstDecoder::decode_slice(&[0xbf, 0x8a, 0xd9]).unwrap();
+    assert_eq!(inst.encode_to(&mut [0u8; 2]), Err(EncodeError::BufferTooSmall { needed: 3 }));
+
+    static EXTENSIONS: [OpcodeExtension; 1] = [
+        OpcodeExtension {
+            pattern: 0xa8,
+            mask: 0xf8,
+            mnemonic: "ldx",
+            operands: [OperandSpec::RegisterLow3, OperandSpec::BranchRelI8],
+            description: "",
+        },
+    ];
+    let decoder = InstDecoder::default().with_extensions(&EXTENSIONS);
+    let inst = decoder.decode(&mut U8Reader::new(&[0xad, 0xf0])).unwrap();
+    let mut buf = [0u8; 2];
+    assert_eq!(inst.encode_to(&mut buf), Ok(2));
+    assert_eq!(buf, [0xad, 0xf0]);
+}
+
+#[test]
+fn test_instruction_bytes() {
+    use yaxpeax_arch::U8Reader;
+    use yaxpeax_avnera::{InstDecoder, Instruction, Opcode, Operand};
+
+    let inst = InstDecoder::decode_slice(&[0xc9, 0xf2, 0xed, 0xb9]).unwrap();
+    assert_eq!(inst.bytes(), &[0xc9, 0xf2, 0xed]);
+
+    let mut reader = U8Reader::new(&[0xb9, 0xe4, 0x0e]);
+    let decoder = InstDecoder::default();
+    assert_eq!(decoder.decode(&mut reader).unwrap().bytes(), &[0xb9]);
+    assert_eq!(decoder.decode(&mut reader).unwrap().bytes(), &[0xe4, 0x0e]);
+
+    let inst = Instruction::build(Opcode::Call, &[Operand::ImmU16 { imm: 0xd98a }]).unwrap();
+    assert_eq!(inst.bytes(), &[0xbf, 0x8a, 0xd9]);
+
+    assert!(Instruction::default().bytes().is_empty());
+
+    // decoding into an instruction leaves nothing of what it held before.
+    let mut inst = InstDecoder::decode_slice(&[0xbf, 0x8a, 0xd9]).unwrap();
+    decoder.decode_into(&mut inst, &mut U8Reader::new(&[0xb9])).unwrap();
+    assert_eq!(inst, InstDecoder::decode_slice(&[0xb9]).unwrap());
+    assert_eq!(format!("{:?}", inst), "Instruction { opcode: ret, operands: [], bytes: [185] }");
+}
+
+#[test]
+fn test_decode_errors() {
+    use yaxpeax_arch::DecodeError as _;
+    use yaxpeax_avnera::{DecodeErrorKind, InstDecoder};
+
+    let err = InstDecoder::decode_slice(&[0xa3, 0x00]).unwrap_err();
+    assert_eq!(err.kind(), DecodeErrorKind::InvalidOpcode);
+    assert_eq!(err.opcode(), Some(0xa3));
+    assert_eq!(err.offset(), 0);
+    assert!(err.bad_opcode());
+    assert_eq!(err.to_string(), "invalid opcode 0xa3");
+
+    let err = InstDecoder::decode_slice(&[0xc9, 0xf2]).unwrap_err();
+    assert_eq!(err.kind(), DecodeErrorKind::ExhaustedInput);
+    assert_eq!(err.opcode(), Some(0xc9));
+    assert_eq!(err.offset(), 2);
+    assert!(err.data_exhausted());
+
+    let err = InstDecoder::decode_slice(&[]).unwrap_err();
+    assert_eq!(err.kind(), DecodeErrorKind::ExhaustedInput);
+    assert_eq!(err.opcode(), None);
+    assert_eq!(err.offset(), 0);
+}
+
+#[test]
+fn test_addr16() {
+    use yaxpeax_avnera::Addr16;
+
+    assert_eq!(Addr16(0xfffe).wrapping_add(3), Addr16(0x0001));
+    assert_eq!(Addr16(0x0001).wrapping_sub(3), Addr16(0xfffe));
+    assert_eq!(Addr16(0x0010).offset(-0x20), Addr16(0xfff0));
+    assert_eq!(Addr16(0xfff0).offset(0x20), Addr16(0x0010));
+
+    assert_eq!(Addr16(0xfff0).distance_to(Addr16(0x0010)), 0x20);
+    assert_eq!(Addr16(0x0010).distance_to(Addr16(0xfff0)), 0xffe0);
+    assert_eq!(Addr16(0x0010).displacement_to(Addr16(0xfff0)), -0x20);
+
+    assert!(Addr16(0xffff).is_within(Addr16(0xfff0), 0x20));
+    assert!(Addr16(0x000f).is_within(Addr16(0xfff0), 0x20));
+    assert!(!Addr16(0x0010).is_within(Addr16(0xfff0), 0x20));
+    assert!(!Addr16(0x1000).is_within(Addr16(0x1000), 0));
+    assert!(Addr16(0x0fff).is_within(Addr16(0x1000), 0x10000));
+
+    assert_eq!(Addr16(0xd98a).to_string(), "0xd98a");
+}
+
+#[test]
+fn test_permissive_decode() {
+    use yaxpeax_arch::U8Reader;
+    use yaxpeax_avnera::{BuildError, DecodeErrorKind, InstDecoder, Instruction, Opcode, Operand};
+
+    let decoder = InstDecoder::permissive();
+
+    // a linear sweep through data with invalid opcodes never errors until input runs out.
+    let data = [0xa0, 0xb9, 0xb8, 0xe4, 0x0e, 0xc9];
+    let mut reader = U8Reader::new(&data);
+    let mut listing = Vec::new();
+    let err = loop {
+        match decoder.decode(&mut reader) {
+            Ok(inst) => listing.push(inst.to_string()),
+            Err(e) => break e,
+        }
+    };
+    assert_eq!(listing, [".byte 0xa0", "ret", ".byte 0xb8", "r4 <- 0x0e"]);
+    assert_eq!(err.kind(), DecodeErrorKind::ExhaustedInput);
+
+    let inst = decoder.decode(&mut U8Reader::new(&[0xa5])).unwrap();
+    assert_eq!(inst.opcode(), Opcode::Undefined);
+    assert_eq!(inst.operand(0), Some(Operand::ImmU8 { imm: 0xa5 }));
+    assert_eq!(inst.bytes(), &[0xa5]);
+    let mut buf = [0u8; 1];
+    assert_eq!(inst.encode_to(&mut buf), Ok(1));
+    assert_eq!(buf, [0xa5]);
+
+    // `.byte 0xb9` would decode as `ret`, so it can't be built.
+    assert_eq!(
+        Instruction::build(Opcode::Undefined, &[Operand::ImmU8 { imm: 0xb9 }]),
+        Err(BuildError::OperandValue { index: 0 })
+    );
+
+    // the default decoder is not permissive.
+    assert!(InstDecoder::decode_slice(&[0xa5]).is_err());
+    assert!(InstDecoder::permissive().with_permissive(false).decode(&mut U8Reader::new(&[0xa5])).is_err());
+}
+
+#[test]
+fn test_strict_decode() {
+    use yaxpeax_arch::U8Reader;
+    use yaxpeax_avnera::{DecodeErrorKind, InstDecoder};
+
+    let strict = InstDecoder::strict();
+    for byte in (0x58..=0x5fu8).chain(0x68..=0x6f) {
+        let res = strict.decode(&mut U8Reader::new(&[byte]));
+        if byte == 0x59 || byte == 0x69 {
+            assert!(res.is_ok(), "scf and ccf are not speculative");
+        } else {
+            assert_eq!(res.unwrap_err().kind(), DecodeErrorKind::InvalidOpcode);
+            assert!(InstDecoder::decode_slice(&[byte]).is_ok());
+        }
+    }
+
+    // strict and permissive together turn speculative encodings into `.byte`.
+    let inst = strict.with_permissive(true).decode(&mut U8Reader::new(&[0x5a])).unwrap();
+    assert_eq!(inst.to_string(), ".byte 0x5a");
+    let mut buf = [0u8; 1];
+    assert_eq!(inst.encode_to(&mut buf), Ok(1));
+    assert_eq!(buf, [0x5a]);
+}
+
+#[test]
+fn test_semantics_known() {
+    use yaxpeax_arch::{Instruction as _, U8Reader};
+    use yaxpeax_avnera::{Confidence, InstDecoder, Opcode};
+
+    let inst = InstDecoder::decode_slice(&[0x29]).unwrap();
+    assert!(inst.semantics_known());
+    assert!(inst.well_defined());
+    assert_eq!(inst.opcode().confidence(), Confidence::Confident);
+
+    let inst = InstDecoder::decode_slice(&[0x92, 0x10]).unwrap();
+    assert!(inst.semantics_known());
+    assert_eq!(inst.opcode().confidence(), Confidence::Tentative);
+
+    for bytes in [[0x5a], [0x6b]].iter() {
+        let inst = InstDecoder::decode_slice(bytes).unwrap();
+        assert!(!inst.semantics_known());
+        assert!(!inst.well_defined());
+    }
+
+    let inst = InstDecoder::permissive().decode(&mut U8Reader::new(&[0xa0])).unwrap();
+    assert!(!inst.well_defined());
+    assert_eq!(Opcode::Undefined.confidence(), Confidence::Unknown);
+}
+
+#[test]
+fn test_bit_access() {
+    use yaxpeax_avnera::{BitAccess, BitOperation, InstDecoder, Instruction, Opcode, Operand, OperandKind, RegSpec};
+
+    let inst = InstDecoder::decode_slice(&[0x65]).unwrap();
+    assert_eq!(inst.operand(0), Some(Operand::BitIndex { bit: 5 }));
+    assert_eq!(inst.operand(0).unwrap().kind(), OperandKind::BitIndex);
+    assert_eq!(inst.operand(0).unwrap().width(), 1);
+    assert_eq!(inst.to_string(), "bit r0, 0x05");
+    assert_eq!(inst.bit_access(), Some(BitAccess {
+        register: RegSpec::r0(),
+        bit: 5,
+        operation: BitOperation::Toggle,
+        operand: 0,
+    }));
+
+    let built = Instruction::build(Opcode::Bit, &[Operand::BitIndex { bit: 5 }]).unwrap();
+    assert_eq!(built.bytes(), &[0x65]);
+
+    assert_eq!(InstDecoder::decode_slice(&[0x5a]).unwrap().bit_access(), None);
+    assert_eq!(InstDecoder::decode_slice(&[0x29]).unwrap().bit_access(), None);
+}
+
+#[test]
+fn test_part_decoders() {
+    use yaxpeax_arch::{Decoder, U8Reader};
+    use yaxpeax_avnera::{InstDecoder, OpcodeExtension, OperandSpec, Part};
+
+    static EXTENSIONS: [OpcodeExtension; 1] = [
+        OpcodeExtension {
+            pattern: 0xa0,
+            mask: 0xf8,
+            mnemonic: "xchg",
+            operands: [OperandSpec::RegisterLow3, OperandSpec::Nothing],
+            description: "exchange r0 and rN?",
+        },
+    ];
+
+    assert_eq!(InstDecoder::default().part(), None);
+
+    let parts = [Part::Av6201, Part::Av6301, Part::Av6302, Part::Av7201, Part::Av7301];
+    for part in parts.iter() {
+        let decoder = InstDecoder::for_part(*part).with_extensions(&EXTENSIONS);
+        assert_eq!(decoder.part(), Some(*part));
+        // with no part-specific opcodes known, every part decodes like the default decoder.
+        for byte in 0..=0xffu8 {
+            let data = [byte, 0x12, 0x34];
+            let expected = InstDecoder::default().with_extensions(&EXTENSIONS).decode(&mut U8Reader::new(&data));
+            assert_eq!(decoder.decode(&mut U8Reader::new(&data)), expected);
+        }
+    }
+}
+
+#[test]
+fn test_opcode_overrides() {
+    use yaxpeax_arch::{Decoder, U8Reader};
+    use yaxpeax_avnera::{InstDecoder, Opcode, OpcodeExtension, OpcodeOverride, Operand, OperandSpec, RegSpec};
+
+    static SWAP: OpcodeExtension = OpcodeExtension {
+        pattern: 0xa0,
+        mask: 0xf8,
+        mnemonic: "swap",
+        operands: [OperandSpec::RegisterLow3, OperandSpec::ImmU8],
+        description: "swap nibbles of rN, then something with an immediate?",
+    };
+    static OVERRIDES: [OpcodeOverride; 3] = [
+        OpcodeOverride {
+            pattern: 0x5a,
+            mask: 0xff,
+            opcode: Opcode::Cmp,
+            operands: [OperandSpec::RegisterLow3, OperandSpec::Nothing],
+        },
+        OpcodeOverride {
+            pattern: 0x58,
+            mask: 0xf8,
+            opcode: Opcode::Bit,
+            operands: [OperandSpec::BitLow3, OperandSpec::Nothing],
+        },
+        OpcodeOverride {
+            pattern: 0xa0,
+            mask: 0xf8,
+            opcode: Opcode::Extension(&SWAP),
+            operands: [OperandSpec::RegisterLow3, OperandSpec::ImmU8],
+        },
+    ];
+
+    let decoder = InstDecoder::default().with_overrides(&OVERRIDES);
+    let decode = |bytes: &[u8]| decoder.decode(&mut U8Reader::new(bytes));
+
+    // the first matching override wins, even over a known opcode like `scf`.
+    let inst = decode(&[0x5a]).unwrap();
+    assert_eq!(inst.opcode(), Opcode::Cmp);
+    assert_eq!(inst.operand(0), Some(Operand::Register { reg: RegSpec::r2() }));
+    assert_eq!(decode(&[0x59]).unwrap().to_string(), "bit r0, 0x01");
+
+    let inst = decode(&[0xa3, 0x42]).unwrap();
+    assert_eq!(inst.to_string(), "swap r3, 0x42");
+    assert_eq!(inst.len(), 2);
+    assert_eq!(inst.bytes(), &[0xa3, 0x42]);
+    assert_eq!(inst.extension(), Some(&SWAP));
+    assert_eq!(decode(&[0xa3]).unwrap_err().offset(), 1);
+
+    // bytes no override matches decode as usual.
+    assert_eq!(decode(&[0x69]).unwrap().to_string(), "ccf");
+    assert_eq!(decode(&[0x6a]).unwrap().opcode(), Opcode::Op6xHi);
+}
+
+#[test]
+fn test_annotations() {
+    use yaxpeax_arch::annotation::{AnnotatingDecoder, DescriptionSink};
+    use yaxpeax_arch::U8Reader;
+    use yaxpeax_avnera::{FieldDescription, InstDecoder, Instruction, Opcode, OpcodeOverride, Operand, OperandSpec, RegPair, RegSpec};
+
+    static OVERRIDES: [OpcodeOverride; 1] = [
+        OpcodeOverride {
+            pattern: 0x58,
+            mask: 0xf8,
+            opcode: Opcode::Bit,
+            operands: [OperandSpec::BitLow3, OperandSpec::Nothing],
+        },
+    ];
+
+    struct Fields(Vec<(u32, u32, FieldDescription)>);
+
+    impl DescriptionSink<FieldDescription> for Fields {
+        fn record(&mut self, start: u32, end: u32, description: FieldDescription) {
+            self.0.push((start, end, description));
+        }
+    }
+
+    fn annotate(bytes: &[u8]) -> Vec<(u32, u32, FieldDescription)> {
+        let mut inst = Instruction::default();
+        let mut fields = Fields(Vec::new());
+        InstDecoder::permissive()
+            .decode_with_annotation(&mut inst, &mut U8Reader::new(bytes), &mut fields)
+            .unwrap();
+        fields.0
+    }
+
+    assert_eq!(annotate(&[0x2b]), [
+        (3, 7, FieldDescription::Opcode { opcode: Opcode::Xor }),
+        (0, 2, FieldDescription::RegisterSelector { operand: 0, value: Operand::Register { reg: RegSpec::r3() } }),
+    ]);
+    assert_eq!(annotate(&[0xe4, 0x0e]), [
+        (3, 7, FieldDescription::Opcode { opcode: Opcode::LoadImm8 }),
+        (0, 2, FieldDescription::RegisterSelector { operand: 0, value: Operand::Register { reg: RegSpec::r4() } }),
+        (8, 15, FieldDescription::Immediate { operand: 1, value: Operand::ImmU8 { imm: 0x0e } }),
+    ]);
+    assert_eq!(annotate(&[0xbf, 0x34, 0x12]), [
+        (0, 7, FieldDescription::Opcode { opcode: Opcode::Call }),
+        (8, 23, FieldDescription::Immediate { operand: 0, value: Operand::ImmU16 { imm: 0x1234 } }),
+    ]);
+    assert_eq!(annotate(&[0x92, 0xfe]), [
+        (3, 7, FieldDescription::Opcode { opcode: Opcode::JccLo }),
+        (0, 2, FieldDescription::Immediate { operand: 0, value: Operand::ImmU8 { imm: 2 } }),
+        (8, 15, FieldDescription::Displacement { operand: 1, value: Operand::BranchRelI8 { rel: -2 } }),
+    ]);
+    assert_eq!(annotate(&[0xfc, 0x40]), [
+        (3, 7, FieldDescription::Opcode { opcode: Opcode::LoadRegPairC }),
+        (0, 2, FieldDescription::RegisterSelector { operand: 0, value: Operand::MemRegIndirectOffset { pair: RegPair::r4_r5(), offs: 0x40 } }),
+        (8, 15, FieldDescription::Displacement { operand: 0, value: Operand::MemRegIndirectOffset { pair: RegPair::r4_r5(), offs: 0x40 } }),
+    ]);
+    assert_eq!(annotate(&[0xa0]), [
+        (0, 7, FieldDescription::Opcode { opcode: Opcode::Undefined }),
+    ]);
+
+    assert_eq!(annotate(&[0x2b])[1].2.to_string(), "register selector (r3)");
+
+    // an overridden opcode byte is described with the fields of the opcode it decodes as.
+    let mut inst = Instruction::default();
+    let mut fields = Fields(Vec::new());
+    InstDecoder::default()
+        .with_overrides(&OVERRIDES)
+        .decode_with_annotation(&mut inst, &mut U8Reader::new(&[0x5a]), &mut fields)
+        .unwrap();
+    assert_eq!(fields.0, [
+        (3, 7, FieldDescription::Opcode { opcode: Opcode::Bit }),
+        (0, 2, FieldDescription::Immediate { operand: 0, value: Operand::BitIndex { bit: 2 } }),
+    ]);
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_colorize() {
+    use yaxpeax_arch::{Colorize, NoColors};
+    use yaxpeax_avnera::{InstDecoder, Opcode};
+
+    // without colors, colorizing writes exactly what `Display` does.
+    for byte in 0..=0xffu8 {
+        let inst = match InstDecoder::decode_slice(&[byte, 0xfe, 0x12]) {
+            Ok(inst) => inst,
+            Err(_) => continue,
+        };
+        let mut out = String::new();
+        inst.colorize(&NoColors, &mut out).unwrap();
+        assert_eq!(out, inst.to_string());
+
+        for op in inst.operands() {
+            let mut out = String::new();
+            op.colorize(&NoColors, &mut out).unwrap();
+            assert_eq!(out, op.to_string());
+        }
+    }
+
+    let mut out = String::new();
+    Opcode::LoadImm8.colorize(&NoColors, &mut out).unwrap();
+    assert_eq!(out, "loadimm8");
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_show_contextual() {
+    use yaxpeax_arch::{NoColors, ShowContextual};
+    use yaxpeax_avnera::{InstDecoder, SymbolResolver};
+
+    let symbols: &[(u16, &str)] = &[
+        (0xd98a, "uart_send"),
+        (0x2000, "rx_buf"),
+        (0x1010, "loop_top"),
+    ];
+    assert_eq!(symbols.symbol(0x2000), Some("rx_buf"));
+    assert_eq!(symbols.symbol(0x2001), None);
+
+    let show = |bytes: &[u8], addr: u16, symbols: Option<&[(u16, &str)]>| {
+        let inst = InstDecoder::decode_slice(bytes).unwrap();
+        let mut out = String::new();
+        inst.contextualize(&NoColors, addr, symbols, &mut out).unwrap();
+        out
+    };
+
+    assert_eq!(show(&[0xbf, 0x8a, 0xd9], 0x1000, Some(symbols)), "call uart_send");
+    assert_eq!(show(&[0xbc, 0x8a, 0xd9], 0x1000, Some(symbols)), "jmp uart_send");
+    assert_eq!(show(&[0xe9, 0x00, 0x20], 0x1000, Some(symbols)), "r1 <- [rx_buf]");
+    assert_eq!(show(&[0xcb, 0x00, 0x20], 0x1000, Some(symbols)), "[rx_buf] <- r3");
+    // `jnz` at 0x1020 branches to 0x1022 - 0x12.
+    assert_eq!(show(&[0x90, 0xee], 0x1020, Some(symbols)), "jnz loop_top");
+    assert_eq!(show(&[0x92, 0xee], 0x1020, Some(symbols)), "jcc.lo.2 loop_top");
+
+    // addresses without a symbol, or without a resolver at all, display as usual.
+    assert_eq!(show(&[0xbf, 0x8b, 0xd9], 0x1000, Some(symbols)), "call 0xd98b");
+    assert_eq!(show(&[0xbf, 0x8a, 0xd9], 0x1000, None), "call 0xd98a");
+    assert_eq!(show(&[0xe4, 0x20], 0x1000, Some(symbols)), "r4 <- 0x20");
+    // a branch target without a symbol is still resolved to an absolute address.
+    assert_eq!(show(&[0x90, 0x10], 0x1020, Some(symbols)), "jnz 0x1032");
+}
+
+#[test]
+fn test_display_at() {
+    use yaxpeax_avnera::InstDecoder;
+
+    let display_at = |bytes: &[u8], addr: u16| {
+        InstDecoder::decode_slice(bytes).unwrap().display_at(addr).to_string()
+    };
+
+    assert_eq!(display_at(&[0x90, 0x50], 0x1234), "jnz 0x1286");
+    assert_eq!(display_at(&[0x99, 0xfe], 0x1234), "jc 0x1234");
+    assert_eq!(display_at(&[0x9b, 0x80], 0x0010), "jcc.hi.3 0xff92");
+    assert_eq!(display_at(&[0x91, 0x7f], 0xffc0), "jnc 0x0041");
+
+    // instructions without relative branches display as they always do.
+    assert_eq!(display_at(&[0xbf, 0x8a, 0xd9], 0x1234), "call 0xd98a");
+    assert_eq!(display_at(&[0xe4, 0x0e], 0x1234), "r4 <- 0x0e");
+}
+
+#[test]
+fn test_address_space_boundaries() {
+    use yaxpeax_avnera::{EncodeError, InstDecoder, InstructionBoundaries};
+
+    // relative branches wrap in both directions at the ends of the address space.
+    let jnz_back = InstDecoder::decode_slice(&[0x90, 0x80]).unwrap();
+    assert_eq!(jnz_back.branch_target(0x0000), Some(0xff82));
+    let jnz_fwd = InstDecoder::decode_slice(&[0x90, 0x7f]).unwrap();
+    assert_eq!(jnz_fwd.branch_target(0xfffe), Some(0x007f));
+    assert_eq!(jnz_fwd.branch_target(0xffff), Some(0x0080));
+
+    // a sweep at the very end of the address space stops at 0xffff, without wrapping to 0.
+    let data = [0x28, 0x29, 0xe4, 0x0e, 0x28];
+    let boundaries = InstructionBoundaries::from_linear_sweep(&InstDecoder::default(), &data, 0xfffe);
+    assert!(boundaries.is_instruction_start(0xfffe));
+    assert!(boundaries.is_instruction_start(0xffff));
+    assert!(!boundaries.is_instruction_start(0x0000));
+    assert!(!boundaries.is_instruction_start(0x0002));
+    assert_eq!(boundaries.prev_instruction(0x0000), None);
+    assert_eq!(boundaries.prev_instruction(0xffff), Some(0xfffe));
+
+    // an instruction starting at 0xfffd is cut off by the end of the address space.
+    let boundaries = InstructionBoundaries::from_linear_sweep(&InstDecoder::default(), &[0xbf, 0x34, 0x12, 0x28], 0xfffd);
+    assert!(boundaries.is_instruction_start(0xfffd));
+    assert!(!boundaries.is_instruction_start(0x0000));
+    let boundaries = InstructionBoundaries::from_linear_sweep(&InstDecoder::default(), &[0x28, 0xbf, 0x34, 0x12], 0xfffd);
+    assert!(boundaries.is_instruction_start(0xfffd));
+    assert!(!boundaries.is_instruction_start(0xfffe));
+
+    let decoder = InstDecoder::default();
+    assert_eq!(decoder.prev_instruction_at(&[0x28, 0x29], 0xfffe, 0xffff).map(|(addr, _)| addr), Some(0xfffe));
+    assert_eq!(decoder.prev_instruction_at(&[0x28, 0x29], 0xfffe, 0x0000), None);
+    assert_eq!(decoder.prev_instruction_at(&[0x28], 0x0000, 0x0000), None);
+
+    // encoding never writes past the end of the buffer it is given.
+    let call = InstDecoder::decode_slice(&[0xbf, 0x34, 0x12]).unwrap();
+    for len in 0..3 {
+        let mut buf = [0xaau8; 3];
+        assert_eq!(call.encode_to(&mut buf[..len]), Err(EncodeError::BufferTooSmall { needed: 3 }));
+        assert_eq!(buf, [0xaa; 3]);
+    }
+}
+
+#[test]
+fn test_display_styles() {
+    use yaxpeax_avnera::{DisplayStyle, InstDecoder};
+
+    let cases: &[(&[u8], &str, &str)] = &[
+        (&[0x29], "r0 ^= r1", "xor r0, r1"),
+        (&[0x1b], "r0 |= r3", "or r0, r3"),
+        (&[0x22], "r0 &= r2", "and r0, r2"),
+        (&[0x55], "r0 += r5", "add r0, r5"),
+        (&[0x0a], "adc r0, r2", "adc r0, r2"),
+        (&[0x7c], "cmp r0, r4", "cmp r0, r4"),
+        (&[0x13], "r0 <- r3", "mov r0, r3"),
+        (&[0x73], "r3 <- r0", "mov r3, r0"),
+        (&[0xe4, 0x0e], "r4 <- 0x0e", "mov r4, 0x0e"),
+        (&[0xe9, 0x00, 0x20], "r1 <- [0x2000]", "mov r1, [0x2000]"),
+        (&[0xcb, 0x00, 0x20], "[0x2000] <- r3", "mov [0x2000], r3"),
+        (&[0xf4], "r0 <- [r4:r5]", "mov r0, [r4:r5]"),
+        (&[0xd6], "[r6:r7] <- r0", "mov [r6:r7], r0"),
+        (&[0xfc, 0x40], "r0 <- [r4:r5 + 0x40]", "mov r0, [r4:r5 + 0x40]"),
+        (&[0xdc, 0x40], "[r4:r5 + 0x40] <- r0", "mov [r4:r5 + 0x40], r0"),
+        (&[0xbf, 0x8a, 0xd9], "call 0xd98a", "call 0xd98a"),
+        (&[0xb9], "ret", "ret"),
+    ];
+
+    for (bytes, pseudocode, mnemonic) in cases.iter() {
+        let inst = InstDecoder::decode_slice(bytes).unwrap();
+        assert_eq!(inst.to_string(), *pseudocode);
+        assert_eq!(inst.display_with(DisplayStyle::Pseudocode).to_string(), *pseudocode);
+        assert_eq!(inst.display_with(DisplayStyle::Mnemonic).to_string(), *mnemonic);
+    }
+
+    assert_eq!(DisplayStyle::default(), DisplayStyle::Pseudocode);
+
+    let inst = InstDecoder::decode_slice(&[0x90, 0x50]).unwrap();
+    assert_eq!(inst.display_at(0x1234).with_style(DisplayStyle::Mnemonic).to_string(), "jnz 0x1286");
+}
+
+#[test]
+#[should_panic(expected = "longer than any avnera instruction")]
+fn test_overlong_extension() {
+    use yaxpeax_avnera::{InstDecoder, OpcodeExtension, OperandSpec};
+
+    static EXTENSIONS: [OpcodeExtension; 1] = [
+        OpcodeExtension {
+            pattern: 0xa1,
+            mask: 0xff,
+            mnemonic: "long",
+            operands: [OperandSpec::ImmU16, OperandSpec::MemAbs16],
+            description: "five bytes is more than any instruction seen",
+        },
+    ];
+
+    assert_eq!(EXTENSIONS[0].len(), 5);
+    InstDecoder::default().with_extensions(&EXTENSIONS);
+}
+
+#[test]
+fn test_extension_bytes() {
+    use yaxpeax_arch::{Decoder, U8Reader};
+    use yaxpeax_avnera::{InstDecoder, OpcodeExtension, OperandSpec};
+
+    static EXTENSIONS: [OpcodeExtension; 2] = [
+        OpcodeExtension {
+            pattern: 0xa1,
+            mask: 0xff,
+            mnemonic: "ldw",
+            operands: [OperandSpec::MemAbs16, OperandSpec::Nothing],
+            description: "load a word?",
+        },
+        OpcodeExtension {
+            pattern: 0xa2,
+            mask: 0xff,
+            mnemonic: "djnz",
+            operands: [OperandSpec::ImmU8, OperandSpec::BranchRelI8],
+            description: "decrement and branch?",
+        },
+    ];
+
+    let decoder = InstDecoder::default().with_extensions(&EXTENSIONS);
+    for bytes in [[0xa1, 0x34, 0x12], [0xa2, 0x05, 0xfe]].iter() {
+        let inst = decoder.decode(&mut U8Reader::new(&bytes[..])).unwrap();
+        assert_eq!(inst.len(), 3);
+        assert_eq!(inst.bytes(), &bytes[..]);
+    }
+}
+
+#[test]
+fn test_invalid_overrides() {
+    use std::panic::catch_unwind;
+    use yaxpeax_arch::{Decoder, U8Reader};
+    use yaxpeax_avnera::{InstDecoder, Opcode, OpcodeExtension, OpcodeOverride, OperandSpec};
+
+    static JCC_WITHOUT_CONDITION: [OpcodeOverride; 1] = [
+        OpcodeOverride {
+            pattern: 0x5a,
+            mask: 0xff,
+            opcode: Opcode::JccLo,
+            operands: [OperandSpec::BranchRelI8, OperandSpec::Nothing],
+        },
+    ];
+    static JMP_REGISTER_PAIR: [OpcodeOverride; 1] = [
+        OpcodeOverride {
+            pattern: 0x58,
+            mask: 0xf8,
+            opcode: Opcode::Jmp,
+            operands: [OperandSpec::RegisterPairLow3, OperandSpec::Nothing],
+        },
+    ];
+    static LONG: OpcodeExtension = OpcodeExtension {
+        pattern: 0x58,
+        mask: 0xf8,
+        mnemonic: "long",
+        operands: [OperandSpec::ImmU16, OperandSpec::MemAbs16],
+        description: "five bytes is more than any instruction seen",
+    };
+    static TOO_LONG: [OpcodeOverride; 1] = [
+        OpcodeOverride {
+            pattern: 0x58,
+            mask: 0xf8,
+            opcode: Opcode::Extension(&LONG),
+            operands: [OperandSpec::ImmU16, OperandSpec::MemAbs16],
+        },
+    ];
+    static JCC_CONDITION_BYTE: [OpcodeOverride; 1] = [
+        OpcodeOverride {
+            pattern: 0x5a,
+            mask: 0xff,
+            opcode: Opcode::JccHi,
+            operands: [OperandSpec::ImmU8, OperandSpec::BranchRelI8],
+        },
+    ];
+    static JCC_ANY_CONDITION: [OpcodeOverride; 1] = [
+        OpcodeOverride {
+            pattern: 0x58,
+            mask: 0xf8,
+            opcode: Opcode::JccLo,
+            operands: [OperandSpec::ImmLow3, OperandSpec::BranchRelI8],
+        },
+    ];
+    static OP5XHI_BYTE: [OpcodeOverride; 1] = [
+        OpcodeOverride {
+            pattern: 0x5b,
+            mask: 0xff,
+            opcode: Opcode::Op5xHi,
+            operands: [OperandSpec::OpcodeByte, OperandSpec::Nothing],
+        },
+    ];
+    static JCC_SOME_CONDITIONS: [OpcodeOverride; 1] = [
+        OpcodeOverride {
+            pattern: 0x5c,
+            mask: 0xfc,
+            opcode: Opcode::JccLo,
+            operands: [OperandSpec::ImmLow3, OperandSpec::BranchRelI8],
+        },
+    ];
+
+    assert!(catch_unwind(|| InstDecoder::default().with_overrides(&JCC_WITHOUT_CONDITION)).is_err());
+    assert!(catch_unwind(|| InstDecoder::default().with_overrides(&JMP_REGISTER_PAIR)).is_err());
+    assert_eq!(TOO_LONG[0].len(), 5);
+    assert!(catch_unwind(|| InstDecoder::default().with_overrides(&TOO_LONG)).is_err());
+
+    // operands of the right kinds are not enough, they must come from where the opcode's do and
+    // have values it can encode.
+    assert!(catch_unwind(|| InstDecoder::default().with_overrides(&JCC_CONDITION_BYTE)).is_err());
+    assert!(catch_unwind(|| InstDecoder::default().with_overrides(&OP5XHI_BYTE)).is_err());
+    assert!(catch_unwind(|| InstDecoder::default().with_overrides(&JCC_ANY_CONDITION)).is_err());
+
+    let decoder = InstDecoder::default().with_overrides(&JCC_SOME_CONDITIONS);
+    let inst = decoder.decode(&mut U8Reader::new(&[0x5d, 0x10])).unwrap();
+    assert_eq!(inst.to_string(), "jcc.lo.5 $+0x10");
+    assert_eq!(inst.branch_target(0x1000), Some(0x1012));
+    let mut buf = [0u8; 3];
+    assert_eq!(inst.encode_to(&mut buf), Ok(2));
+    assert_eq!(buf[..2], [0x95, 0x10]);
+}
+
+#[test]
+fn test_display_styles_with_symbols() {
+    use yaxpeax_avnera::{DisplayStyle, InstDecoder};
+
+    let symbols: &[(u16, &str)] = &[(0xd98a, "uart_send"), (0x2000, "rx_buf"), (0x1010, "loop_top")];
+
+    let show = |bytes: &[u8], addr: u16, style: DisplayStyle| {
+        let inst = InstDecoder::decode_slice(bytes).unwrap();
+        inst.display_at(addr).with_style(style).with_symbols(symbols).to_string()
+    };
+
+    assert_eq!(show(&[0xbf, 0x8a, 0xd9], 0x1000, DisplayStyle::Mnemonic), "call uart_send");
+    assert_eq!(show(&[0xe9, 0x00, 0x20], 0x1000, DisplayStyle::Mnemonic), "mov r1, [rx_buf]");
+    assert_eq!(show(&[0xcb, 0x00, 0x20], 0x1000, DisplayStyle::Mnemonic), "mov [rx_buf], r3");
+    assert_eq!(show(&[0xe9, 0x00, 0x20], 0x1000, DisplayStyle::Pseudocode), "r1 <- [rx_buf]");
+    assert_eq!(show(&[0x90, 0xee], 0x1020, DisplayStyle::Mnemonic), "jnz loop_top");
+    assert_eq!(show(&[0x90, 0x10], 0x1020, DisplayStyle::Mnemonic), "jnz 0x1032");
+
+    // without an address, branch targets can't be resolved, but absolute addresses still can.
+    let jnz = InstDecoder::decode_slice(&[0x90, 0xee]).unwrap();
+    assert_eq!(jnz.display_with(DisplayStyle::Mnemonic).with_symbols(symbols).to_string(), "jnz $-0x12");
+    let jnz = InstDecoder::decode_slice(&[0x90, 0x80]).unwrap();
+    assert_eq!(jnz.display_with(DisplayStyle::Mnemonic).with_symbols(symbols).to_string(), "jnz $-0x80");
+    let call = InstDecoder::decode_slice(&[0xbf, 0x8a, 0xd9]).unwrap();
+    assert_eq!(call.display_with(DisplayStyle::Mnemonic).with_symbols(symbols).to_string(), "call uart_send");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_std_errors() {
+    use std::error::Error;
+    use yaxpeax_avnera::{Instruction, Opcode};
+
+    fn build_jmp() -> Result<Instruction, Box<dyn Error>> {
+        Ok(Instruction::build(Opcode::Jmp, &[])?)
+    }
+    assert_eq!(build_jmp().unwrap_err().to_string(), "expected 1 operands");
+
+    fn encode_call(buf: &mut [u8]) -> Result<usize, Box<dyn Error>> {
+        let call = yaxpeax_avnera::InstDecoder::decode_slice(&[0xbf, 0x8a, 0xd9])?;
+        Ok(call.encode_to(buf)?)
+    }
+    assert_eq!(encode_call(&mut [0u8; 2]).unwrap_err().to_string(), "buffer too small, 3 bytes needed");
+}